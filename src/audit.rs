@@ -2,17 +2,24 @@
 //!
 //! [npm-audit](https://docs.npmjs.com/cli/v7/commands/npm-audit)
 
+use crate::decode_output;
+use crate::exit_code::found_something;
+use crate::extract::{scan_json_object, JsonScan};
+use crate::range::VersionRange;
+use crate::version::{probe_npm_version_with, NpmVersion};
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::str::from_utf8;
+use std::time::{Duration, Instant};
 use tracing::{debug, warn};
+use versions::{SemVer, Versioning};
 
 /// This is used to return the data from audit()
 /// but not used for parsing since we can not easily tell
 /// serde how to decide which to use and the untagged union
 /// error messages are not great
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase", untagged)]
 pub enum NpmAuditData {
     /// audit report version 1 (npm 6 or below)
@@ -21,8 +28,443 @@ pub enum NpmAuditData {
     Version2(NpmAuditDataV2),
 }
 
+impl NpmAuditData {
+    /// all distinct CVE identifiers mentioned in the report
+    ///
+    /// for report version 1 these come from the cves field of the advisories,
+    /// report version 2 does not include a dedicated field so the url and
+    /// title of the vulnerabilities are scanned for CVE identifiers
+    pub fn cves(&self) -> BTreeSet<Cve> {
+        match self {
+            NpmAuditData::Version1(data) => {
+                data.advisories.values().flat_map(|a| a.cve_ids()).collect()
+            }
+            NpmAuditData::Version2(data) => data
+                .vulnerabilities
+                .values()
+                .flat_map(|p| p.via.iter().flat_map(|v| v.cve_ids()))
+                .collect(),
+        }
+    }
+
+    /// the worst severity of the vulnerabilities affecting each package, by
+    /// package name
+    pub fn package_severities(&self) -> BTreeMap<String, Severity> {
+        let mut severities: BTreeMap<String, Severity> = BTreeMap::new();
+        match self {
+            NpmAuditData::Version1(data) => {
+                for advisory in data.advisories.values() {
+                    if let Some(name) = advisory.package_name() {
+                        let severity = severities
+                            .entry(name.to_string())
+                            .or_insert(advisory.severity);
+                        *severity = (*severity).max(advisory.severity);
+                    }
+                }
+            }
+            NpmAuditData::Version2(data) => {
+                for (name, package) in &data.vulnerabilities {
+                    severities.insert(name.to_string(), package.severity);
+                }
+            }
+        }
+        severities
+    }
+
+    /// is the CVE present in the report without a fix available
+    ///
+    /// for report version 1 an advisory counts as fixed if an install or
+    /// update action resolves it and no review action is needed for any of
+    /// its paths, for report version 2 a package counts as fixed if npm
+    /// reports a fix as available (see [NpmAuditData::cves] on how CVEs are
+    /// found in version 2 reports). Returns false if the CVE can not be
+    /// parsed.
+    pub fn has_unfixed_cve(&self, cve: &str) -> bool {
+        let Some(cve) = Cve::parse(cve) else {
+            return false;
+        };
+        match self {
+            NpmAuditData::Version1(data) => data
+                .advisories
+                .values()
+                .filter(|a| a.cve_ids().contains(&cve))
+                .any(|a| !data.is_fixed_by_actions(a.id)),
+            NpmAuditData::Version2(data) => data
+                .vulnerabilities
+                .values()
+                .filter(|p| p.via.iter().any(|v| v.cve_ids().contains(&cve)))
+                .any(|p| !p.fix_available.is_available()),
+        }
+    }
+
+    /// the number of distinct CVE identifiers in the report
+    ///
+    /// a CVE affecting multiple packages is only counted once
+    pub fn distinct_cve_count(&self) -> usize {
+        self.cves().len()
+    }
+
+    /// all advisories in the report in a form independent of the report
+    /// version
+    ///
+    /// for report version 1 there is one entry per advisory, dev_only is set
+    /// if all resolves of the actions for it are due to dev dependencies.
+    /// For report version 2 there is one entry per entry with full details in
+    /// the via lists, the report contains no information about dev
+    /// dependencies so dev_only is always false, see
+    /// [NpmAuditData::iter_vulnerabilities_with_prod_report] to determine it.
+    pub fn iter_vulnerabilities(&self) -> impl Iterator<Item = UnifiedVulnerability> + '_ {
+        let vulnerabilities: Vec<UnifiedVulnerability> = match self {
+            NpmAuditData::Version1(data) => data
+                .advisories
+                .values()
+                .map(|advisory| UnifiedVulnerability {
+                    id: advisory.id,
+                    package: advisory.package_name().unwrap_or_default().to_string(),
+                    title: advisory.title.to_owned(),
+                    url: advisory.url.to_owned(),
+                    severity: advisory.severity,
+                    range: advisory.vulnerable_versions.to_owned().unwrap_or_default(),
+                    cves: advisory.cve_ids(),
+                    dev_only: data.is_dev_only(advisory.id),
+                })
+                .collect(),
+            NpmAuditData::Version2(data) => data
+                .vulnerabilities
+                .iter()
+                .flat_map(|(package, vulnerable_package)| {
+                    vulnerable_package
+                        .via
+                        .iter()
+                        .filter_map(move |vulnerability| match vulnerability {
+                            Vulnerability::NameOnly(_) | Vulnerability::SourceId(_) => None,
+                            Vulnerability::Full {
+                                source,
+                                title,
+                                url,
+                                severity,
+                                range,
+                                ..
+                            } => Some(UnifiedVulnerability {
+                                id: *source,
+                                package: package.to_owned(),
+                                title: title.to_owned(),
+                                url: url.to_owned(),
+                                severity: *severity,
+                                range: range.to_owned(),
+                                cves: vulnerability.cve_ids(),
+                                dev_only: false,
+                            }),
+                        })
+                })
+                .collect(),
+        };
+        vulnerabilities.into_iter()
+    }
+
+    /// like [NpmAuditData::iter_vulnerabilities] but dev_only is set for all
+    /// vulnerabilities of packages which are not in the given report
+    ///
+    /// the given report should be the result of an audit of the same project
+    /// with dev dependencies omitted (see [AuditCommand::omit]), this works
+    /// for both report versions
+    pub fn iter_vulnerabilities_with_prod_report<'a>(
+        &'a self,
+        prod_report: &NpmAuditData,
+    ) -> impl Iterator<Item = UnifiedVulnerability> + 'a {
+        let dev_only_packages = self.dev_only_packages(prod_report);
+        self.iter_vulnerabilities().map(move |mut vulnerability| {
+            vulnerability.dev_only = dev_only_packages.contains(&vulnerability.package);
+            vulnerability
+        })
+    }
+
+    /// the names of the vulnerable packages in this report which are not
+    /// vulnerable in the given report
+    ///
+    /// the given report should be the result of an audit of the same project
+    /// with dev dependencies omitted (see [AuditCommand::omit]) so these are
+    /// the packages only affected through dev dependencies. This works for
+    /// both report versions, unlike [NpmAuditDataV1::is_dev_only] version 2
+    /// reports contain no information about dev dependencies themselves.
+    pub fn dev_only_packages(&self, prod_report: &NpmAuditData) -> BTreeSet<String> {
+        let prod_packages = prod_report.package_severities();
+        self.package_severities()
+            .into_keys()
+            .filter(|package| !prod_packages.contains_key(package))
+            .collect()
+    }
+
+    /// a hash of the issues in the report which does not depend on the order
+    /// npm emitted them in
+    ///
+    /// this hashes the sorted set of advisory id, package name, severity and
+    /// sorted CVE identifiers of the entries of
+    /// [NpmAuditData::iter_vulnerabilities], so changes to e.g. the advisory
+    /// URL do not change it while a changed severity does. Reports with the
+    /// same issues have the same fingerprint. The hash function (64 bit
+    /// FNV-1a) does not change between runs or builds so fingerprints can be
+    /// stored.
+    pub fn fingerprint(&self) -> u64 {
+        let issues: BTreeSet<(u64, String, Severity, BTreeSet<Cve>)> = self
+            .iter_vulnerabilities()
+            .map(|v| (v.id, v.package, v.severity, v.cves.into_iter().collect()))
+            .collect();
+        let mut hash = FNV_OFFSET_BASIS;
+        for (id, package, severity, cves) in &issues {
+            hash = fnv1a(hash, &id.to_le_bytes());
+            // the terminating 0 separates the strings so their boundaries
+            // are part of the hash
+            for field in [package.as_str(), severity.as_str()] {
+                hash = fnv1a(hash, field.as_bytes());
+                hash = fnv1a(hash, &[0]);
+            }
+            for cve in cves {
+                hash = fnv1a(hash, cve.as_str().as_bytes());
+                hash = fnv1a(hash, &[0]);
+            }
+            // separates the issues like the 0 separates the strings
+            hash = fnv1a(hash, &[1]);
+        }
+        hash
+    }
+
+    /// the total number of dependencies according to the report metadata
+    pub fn dependency_total(&self) -> u32 {
+        match self {
+            NpmAuditData::Version1(data) => data.metadata.total_dependencies,
+            NpmAuditData::Version2(data) => data.metadata.dependencies.total,
+        }
+    }
+
+    /// the fraction of all dependencies which are affected by at least one
+    /// vulnerability, between 0.0 and 1.0
+    ///
+    /// returns None if the report has no dependencies
+    pub fn vulnerable_fraction(&self) -> Option<f64> {
+        let total = self.dependency_total();
+        if total == 0 {
+            return None;
+        }
+        Some(self.package_severities().len() as f64 / f64::from(total))
+    }
+
+    /// the number of vulnerabilities of each severity according to the
+    /// report metadata
+    pub fn severity_counts(&self) -> BTreeMap<Severity, u32> {
+        let (info, low, moderate, high, critical) = match self {
+            NpmAuditData::Version1(data) => {
+                let c = &data.metadata.vulnerabilities;
+                (c.info, c.low, c.moderate, c.high, c.critical)
+            }
+            NpmAuditData::Version2(data) => {
+                let c = &data.metadata.vulnerabilities;
+                (c.info, c.low, c.moderate, c.high, c.critical)
+            }
+        };
+        BTreeMap::from([
+            (Severity::Info, info),
+            (Severity::Low, low),
+            (Severity::Moderate, moderate),
+            (Severity::High, high),
+            (Severity::Critical, critical),
+        ])
+    }
+
+    /// the vulnerability and dependency counts of the report in a form
+    /// independent of the report version
+    pub fn summary(&self) -> AuditSummary {
+        let counts = self.severity_counts();
+        AuditSummary {
+            info: counts[&Severity::Info],
+            low: counts[&Severity::Low],
+            moderate: counts[&Severity::Moderate],
+            high: counts[&Severity::High],
+            critical: counts[&Severity::Critical],
+            dependencies: self.dependency_total(),
+        }
+    }
+
+    /// the worst severity of any vulnerability in the report according to
+    /// the report metadata, [Severity::None] if there are none
+    pub fn max_severity(&self) -> Severity {
+        self.severity_counts()
+            .into_iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(severity, _)| severity)
+            .max()
+            .unwrap_or(Severity::None)
+    }
+
+    /// the number of vulnerabilities with the given severity or worse
+    /// according to the report metadata
+    ///
+    /// this is the number npm compares against for `--audit-level`
+    pub fn count_at_least(&self, threshold: Severity) -> usize {
+        self.severity_counts()
+            .into_iter()
+            .filter(|(severity, _)| *severity >= threshold)
+            .map(|(_, count)| count as usize)
+            .sum()
+    }
+
+    /// the share of each severity in all vulnerabilities in percent, based
+    /// on [NpmAuditData::severity_counts]
+    ///
+    /// the percentages add up to 100 apart from rounding, the map is empty if
+    /// the report contains no vulnerabilities
+    pub fn severity_percentages(&self) -> BTreeMap<Severity, f64> {
+        let counts = self.severity_counts();
+        let total: u32 = counts.values().sum();
+        if total == 0 {
+            return BTreeMap::new();
+        }
+        counts
+            .into_iter()
+            .map(|(severity, count)| (severity, f64::from(count) * 100.0 / f64::from(total)))
+            .collect()
+    }
+}
+
+/// FNV-1a offset basis, the initial value for [fnv1a]
+pub(crate) const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+
+/// continues a 64 bit FNV-1a hash with the given bytes
+///
+/// unlike the std hashers this does not change between runs or builds
+pub(crate) fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    /// FNV-1a prime
+    const PRIME: u64 = 0x100000001b3;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(PRIME);
+    }
+    hash
+}
+
+/// A single advisory affecting a package, independent of the report version
+///
+/// returned by [NpmAuditData::iter_vulnerabilities]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct UnifiedVulnerability {
+    /// the advisory id, the id of version 1 advisories and the source of
+    /// version 2 vulnerabilities
+    pub id: u64,
+    /// the name of the affected package
+    ///
+    /// empty if a version 1 advisory contains no package name
+    pub package: String,
+    /// the human readable title of the advisory
+    pub title: String,
+    /// an URL explaining the advisory
+    pub url: String,
+    /// the severity of the advisory
+    pub severity: Severity,
+    /// the affected version range
+    ///
+    /// empty if a version 1 advisory contains no range
+    pub range: String,
+    /// the CVE identifiers of the advisory
+    pub cves: Vec<Cve>,
+    /// is the package only used as a dev dependency
+    ///
+    /// reporters can use this to show these separately without failing on
+    /// them
+    pub dev_only: bool,
+}
+
+/// The vulnerability and dependency counts of a report
+///
+/// returned by [NpmAuditData::summary]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AuditSummary {
+    /// Number of info level vulnerabilities
+    pub info: u32,
+    /// Number of low level vulnerabilities
+    pub low: u32,
+    /// Number of moderate level vulnerabilities
+    pub moderate: u32,
+    /// Number of high level vulnerabilities
+    pub high: u32,
+    /// Number of critical level vulnerabilities
+    pub critical: u32,
+    /// Total number of dependencies
+    pub dependencies: u32,
+}
+
+impl AuditSummary {
+    /// the number of vulnerabilities of all severities
+    pub fn total(&self) -> u32 {
+        self.info + self.low + self.moderate + self.high + self.critical
+    }
+}
+
+/// A CVE (common vulnerabilities and exposures) identifier, e.g.
+/// CVE-2021-23337
+///
+/// always stored in upper case with the CVE- prefix
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize)]
+pub struct Cve(String);
+
+impl Cve {
+    /// parses a CVE identifier
+    ///
+    /// matching is case-insensitive and the CVE- prefix is optional
+    pub fn parse(s: &str) -> Option<Cve> {
+        let s = s.trim();
+        let id = match s.get(..4) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("cve-") => &s[4..],
+            _ => s,
+        };
+        let (year, number) = id.split_once('-')?;
+        if year.len() == 4
+            && number.len() >= 4
+            && year.chars().all(|c| c.is_ascii_digit())
+            && number.chars().all(|c| c.is_ascii_digit())
+        {
+            Some(Cve(format!("CVE-{}-{}", year, number)))
+        } else {
+            None
+        }
+    }
+
+    /// the identifier as a string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for Cve {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// finds all CVE identifiers (CVE-YYYY-NNNN...) mentioned in a text
+pub fn extract_cves(text: &str) -> Vec<Cve> {
+    let bytes = text.as_bytes();
+    let mut cves = Vec::new();
+    let mut i = 0;
+    while i + 4 <= bytes.len() {
+        let at_word_start = i == 0 || !bytes[i - 1].is_ascii_alphanumeric();
+        if at_word_start && bytes[i..i + 4].eq_ignore_ascii_case(b"cve-") {
+            let end = bytes[i + 4..]
+                .iter()
+                .position(|b| !(b.is_ascii_digit() || *b == b'-'))
+                .map_or(bytes.len(), |p| p + i + 4);
+            if let Some(cve) = Cve::parse(&text[i..end]) {
+                cves.push(cve);
+            }
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    cves
+}
+
 /// audit report version 1
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct NpmAuditDataV1 {
     /// UUID identitying the run of npm-audit
@@ -41,14 +483,219 @@ pub struct NpmAuditDataV1 {
     pub metadata: MetadataV1,
 }
 
+impl NpmAuditDataV1 {
+    /// is the advisory resolved by install or update actions without any
+    /// path needing a review
+    pub(crate) fn is_fixed_by_actions(&self, advisory_id: u64) -> bool {
+        let mut fixed = false;
+        for action in &self.actions {
+            let (resolves, fixes) = match action {
+                Action::Install { resolves, .. } | Action::Update { resolves, .. } => {
+                    (resolves, true)
+                }
+                Action::Review { resolves, .. } => (resolves, false),
+            };
+            if resolves.iter().any(|r| r.id == advisory_id) {
+                if !fixes {
+                    return false;
+                }
+                fixed = true;
+            }
+        }
+        fixed
+    }
+
+    /// the resolves of all actions
+    fn resolves(&self) -> impl Iterator<Item = &Resolves> {
+        self.actions.iter().flat_map(|action| match action {
+            Action::Install { resolves, .. }
+            | Action::Update { resolves, .. }
+            | Action::Review { resolves, .. } => resolves.iter(),
+        })
+    }
+
+    /// the ids of all advisories resolved by any of the actions
+    ///
+    /// this includes advisories which only need a review, advisories not in
+    /// this set have no action npm could recommend for them
+    pub fn resolved_advisory_ids(&self) -> BTreeSet<u64> {
+        self.resolves().map(|r| r.id).collect()
+    }
+
+    /// is the advisory only reached through dev dependencies according to the
+    /// resolves of the actions
+    ///
+    /// false if no action resolves the advisory since nothing is known then
+    pub fn is_dev_only(&self, advisory_id: u64) -> bool {
+        let mut resolves = self.resolves().filter(|r| r.id == advisory_id).peekable();
+        resolves.peek().is_some() && resolves.all(|r| r.dev)
+    }
+
+    /// the advisory created most recently, on ties the one with the higher id
+    ///
+    /// report version 2 contains no dates so there is no equivalent there
+    pub fn most_recent_advisory(&self) -> Option<&Advisory> {
+        self.advisories.values().max_by_key(|a| (a.created, a.id))
+    }
+
+    /// the install and update actions as a list of changes, in the order npm
+    /// recommends them
+    ///
+    /// review actions are not included since they do not change anything
+    pub fn remediation_plan(&self) -> Vec<PlannedChange> {
+        self.actions
+            .iter()
+            .filter_map(|action| {
+                let (module, target, is_major, resolves) = match action {
+                    Action::Install {
+                        module,
+                        target,
+                        is_major,
+                        resolves,
+                        ..
+                    } => (module, target, *is_major, resolves),
+                    Action::Update {
+                        module,
+                        target,
+                        resolves,
+                        ..
+                    } => (module, target, false, resolves),
+                    Action::Review { .. } => return None,
+                };
+                let advisory_ids: BTreeSet<u64> = resolves.iter().map(|r| r.id).collect();
+                Some(PlannedChange {
+                    module: module.to_owned(),
+                    target: target.to_owned(),
+                    is_major,
+                    advisory_ids: advisory_ids.into_iter().collect(),
+                })
+            })
+            .collect()
+    }
+
+    /// the advisories whose url does not point to one of the allowed domains
+    /// or one of their subdomains
+    ///
+    /// domains are compared case-insensitively, advisories whose url has no
+    /// domain (see [Advisory::url_domain]) are always included since their
+    /// link can not be checked
+    pub fn unexpected_url_domains(&self, allowed: &[&str]) -> Vec<&Advisory> {
+        self.advisories
+            .values()
+            .filter(|advisory| match advisory.url_domain() {
+                Some(domain) => {
+                    let domain = domain.to_ascii_lowercase();
+                    !allowed.iter().any(|allowed| {
+                        let allowed = allowed.to_ascii_lowercase();
+                        domain == allowed || domain.ends_with(&format!(".{}", allowed))
+                    })
+                }
+                None => true,
+            })
+            .collect()
+    }
+
+    /// the number of advisories for each value of their access field, e.g.
+    /// public or private
+    pub fn count_by_access(&self) -> BTreeMap<String, u32> {
+        let mut counts = BTreeMap::new();
+        for advisory in self.advisories.values() {
+            *counts.entry(advisory.access.to_owned()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// the advisories grouped by the numbers of their CWE identifiers
+    ///
+    /// an advisory with multiple CWEs is listed under each of them, advisories
+    /// without any parseable CWE are skipped
+    pub fn advisories_by_cwe(&self) -> BTreeMap<u32, Vec<&Advisory>> {
+        let mut by_cwe: BTreeMap<u32, Vec<&Advisory>> = BTreeMap::new();
+        for advisory in self.advisories.values() {
+            let mut cwes = advisory.cwe_numbers();
+            cwes.sort_unstable();
+            cwes.dedup();
+            for cwe in cwes {
+                by_cwe.entry(cwe).or_default().push(advisory);
+            }
+        }
+        by_cwe
+    }
+
+    /// the advisories listing the given CVE, e.g. CVE-2021-23337
+    ///
+    /// matching is case-insensitive and the CVE- prefix is optional, see
+    /// [Cve::parse]. One CVE is often listed by several advisories, e.g. for
+    /// a package and its forks.
+    pub fn find_by_cve(&self, cve: &str) -> Vec<&Advisory> {
+        let Some(cve) = Cve::parse(cve) else {
+            return vec![];
+        };
+        self.advisories
+            .values()
+            .filter(|a| a.cve_ids().contains(&cve))
+            .collect()
+    }
+
+    /// the distinct names of the packages at the end of the dependency paths
+    /// the advisory was found at
+    ///
+    /// returns an empty list if there is no advisory with that id
+    pub fn packages_affected_by(&self, advisory_id: u64) -> Vec<String> {
+        let mut packages = BTreeSet::new();
+        for advisory in self.advisories.values().filter(|a| a.id == advisory_id) {
+            for finding in &advisory.findings {
+                for path in &finding.paths {
+                    if let Some(leaf) = path.last() {
+                        packages.insert(leaf.to_owned());
+                    }
+                }
+            }
+        }
+        packages.into_iter().collect()
+    }
+
+    /// the distinct first elements of all dependency paths in the findings of
+    /// the advisories and in the resolves of the actions
+    ///
+    /// these are the direct dependencies of the project which lead to at
+    /// least one advisory
+    pub fn path_roots(&self) -> BTreeSet<String> {
+        let finding_paths = self
+            .advisories
+            .values()
+            .flat_map(|a| a.findings.iter())
+            .flat_map(|f| f.paths.iter());
+        let resolves_paths = self.resolves().map(|r| &r.path);
+        finding_paths
+            .chain(resolves_paths)
+            .filter_map(|path| path.first())
+            .cloned()
+            .collect()
+    }
+}
+
+/// splits a module path like `a>b>c` at the separator
+///
+/// whitespace around the segments is removed since npm has written the
+/// paths both with and without spaces around the separator, so `a > b > c`
+/// is split the same way. Other tools use e.g. `.` as separator.
+pub fn split_module_path(path: &str, separator: &str) -> Vec<String> {
+    path.split(separator)
+        .map(|s| s.trim().to_string())
+        .collect()
+}
+
 /// helper to parse module paths
+///
+/// the path is split at `>`, see [split_module_path]
 pub fn deserialize_module_path<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
     let s = String::deserialize(deserializer)?;
 
-    Ok(s.split('>').map(|s| s.to_string()).collect())
+    Ok(split_module_path(&s, ">"))
 }
 
 /// helper to serialize module paths
@@ -68,10 +715,7 @@ where
 {
     let xs = <Vec<String>>::deserialize(deserializer)?;
 
-    Ok(xs
-        .into_iter()
-        .map(|x| x.split('>').map(|s| s.to_string()).collect())
-        .collect())
+    Ok(xs.iter().map(|x| split_module_path(x, ">")).collect())
 }
 
 /// helper to serialize Vec of module paths
@@ -150,12 +794,77 @@ where
     }
 }
 
+/// converts the timestamp of an advisory for the chrono feature
+///
+/// every [time::OffsetDateTime] is in the range of [chrono::DateTime] so the
+/// default is never used
+#[cfg(feature = "chrono")]
+fn to_chrono(t: time::OffsetDateTime) -> chrono::DateTime<chrono::Utc> {
+    chrono::DateTime::from_timestamp(t.unix_timestamp(), t.nanosecond()).unwrap_or_default()
+}
+
+/// helper to parse optional numbers which some npm builds emit as a string
+/// of digits instead of a JSON number
+pub fn deserialize_optional_u32_or_string<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    /// the two representations of the number we accept
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString {
+        /// a regular JSON number
+        Number(u32),
+        /// a string containing only digits
+        String(String),
+    }
+
+    match <Option<NumberOrString> as Deserialize<'de>>::deserialize(deserializer)? {
+        Some(NumberOrString::Number(n)) => Ok(Some(n)),
+        Some(NumberOrString::String(s)) => {
+            s.trim().parse().map(Some).map_err(serde::de::Error::custom)
+        }
+        None => Ok(None),
+    }
+}
+
+/// helper to parse people like the finder or reporter of an advisory which
+/// some registries and npm 6 emit as an object with a name instead of a
+/// string
+///
+/// an empty name is treated like a missing one
+pub fn deserialize_optional_name<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    /// the two representations of a person we accept
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NameOrObject {
+        /// just the name
+        Name(String),
+        /// an object with the name as a field
+        Object {
+            /// the name of the person
+            #[serde(default)]
+            name: Option<String>,
+        },
+    }
+
+    let name = match <Option<NameOrObject> as Deserialize<'de>>::deserialize(deserializer)? {
+        Some(NameOrObject::Name(name)) => Some(name),
+        Some(NameOrObject::Object { name }) => name,
+        None => None,
+    };
+    Ok(name.filter(|name| !name.is_empty()))
+}
+
 /// advisory in report version 1
 ///
 /// there is a field metadata in the output here but since I could not find
 /// information on its structure it is not parsed (was always null for me)
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct Advisory {
     /// numeric id
     pub id: u64,
@@ -183,8 +892,10 @@ pub struct Advisory {
     /// a CWE (common weakness enumeration) identifier
     pub cwe: Option<Vec<String>>,
     /// who found this security issue
+    #[serde(default, deserialize_with = "deserialize_optional_name")]
     pub found_by: Option<String>,
     /// who reported this security issue
+    #[serde(default, deserialize_with = "deserialize_optional_name")]
     pub reported_by: Option<String>,
     /// when was this advisory created
     #[serde(
@@ -214,27 +925,251 @@ pub struct Advisory {
     pub url: String,
 }
 
+impl Advisory {
+    /// [Advisory::created] as a [chrono::DateTime], requires the chrono
+    /// feature
+    #[cfg(feature = "chrono")]
+    pub fn created_chrono(&self) -> chrono::DateTime<chrono::Utc> {
+        to_chrono(self.created)
+    }
+
+    /// [Advisory::updated] as a [chrono::DateTime], requires the chrono
+    /// feature
+    #[cfg(feature = "chrono")]
+    pub fn updated_chrono(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.updated.map(to_chrono)
+    }
+
+    /// [Advisory::deleted] as a [chrono::DateTime], requires the chrono
+    /// feature
+    #[cfg(feature = "chrono")]
+    pub fn deleted_chrono(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.deleted.map(to_chrono)
+    }
+
+    /// the overview with basic markdown (headings, links, emphasis, inline
+    /// code and code fences) stripped, for places which can not render
+    /// markdown
+    ///
+    /// the raw markdown remains available in the overview field
+    pub fn overview_plaintext(&self) -> String {
+        markdown_to_plaintext(&self.overview)
+    }
+
+    /// the name of the affected package
+    ///
+    /// this is the module name if npm included it, otherwise the last
+    /// element of the first finding path
+    pub fn package_name(&self) -> Option<&str> {
+        self.module_name.as_deref().or_else(|| {
+            self.findings
+                .iter()
+                .flat_map(|f| f.paths.iter())
+                .find_map(|p| p.last())
+                .map(|s| s.as_str())
+        })
+    }
+
+    /// the CVE identifiers listed for this advisory
+    pub fn cve_ids(&self) -> Vec<Cve> {
+        self.cves
+            .iter()
+            .flatten()
+            .filter_map(|c| Cve::parse(c))
+            .collect()
+    }
+
+    /// the host part of the advisory url, e.g. github.com
+    ///
+    /// None if the url has no scheme or an empty host
+    pub fn url_domain(&self) -> Option<&str> {
+        let (_, rest) = self.url.split_once("://")?;
+        let authority = rest.split(['/', '?', '#']).next()?;
+        let host = authority.rsplit('@').next()?;
+        let host = if host.starts_with('[') {
+            host.split_inclusive(']').next()?
+        } else {
+            host.split(':').next()?
+        };
+        if host.is_empty() {
+            None
+        } else {
+            Some(host)
+        }
+    }
+
+    /// the numbers of the CWE identifiers listed for this advisory, entries
+    /// which can not be parsed are skipped
+    pub fn cwe_numbers(&self) -> Vec<u32> {
+        self.cwe
+            .iter()
+            .flatten()
+            .filter_map(|c| parse_cwe(c))
+            .collect()
+    }
+}
+
+/// parses a CWE (common weakness enumeration) identifier like CWE-79 into
+/// its number
+///
+/// matching is case-insensitive and the CWE- prefix is optional
+pub fn parse_cwe(s: &str) -> Option<u32> {
+    let s = s.trim();
+    let number = match s.get(..4) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("cwe-") => &s[4..],
+        _ => s,
+    };
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    number.parse().ok()
+}
+
+/// strips the markdown constructs used in advisory overviews
+fn markdown_to_plaintext(markdown: &str) -> String {
+    let mut lines = Vec::new();
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            continue;
+        }
+        let heading = trimmed.trim_start_matches('#');
+        let line =
+            if heading.len() < trimmed.len() && (heading.is_empty() || heading.starts_with(' ')) {
+                heading.trim_start()
+            } else if let Some(quoted) = trimmed.strip_prefix('>') {
+                quoted.trim_start()
+            } else {
+                line
+            };
+        let line = match line.strip_prefix("* ").or_else(|| line.strip_prefix("+ ")) {
+            Some(item) => format!("- {}", strip_markdown_inline(item)),
+            None => strip_markdown_inline(line),
+        };
+        lines.push(line.trim_end().to_string());
+    }
+    lines.join("\n").trim().to_string()
+}
+
+/// strips inline markdown (links, images, inline code, emphasis) from a
+/// single line
+fn strip_markdown_inline(line: &str) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let mut result = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let image = c == '!' && chars.get(i + 1) == Some(&'[');
+        if c == '[' || image {
+            let start = if image { i + 2 } else { i + 1 };
+            if let Some(close) = find_char(&chars, start, ']') {
+                if chars.get(close + 1) == Some(&'(') {
+                    if let Some(end) = find_char(&chars, close + 2, ')') {
+                        let text: String = chars[start..close].iter().collect();
+                        result.push_str(&strip_markdown_inline(&text));
+                        i = end + 1;
+                        continue;
+                    }
+                }
+            }
+        } else if c == '`' {
+            if let Some(end) = find_char(&chars, i + 1, '`') {
+                result.extend(&chars[i + 1..end]);
+                i = end + 1;
+                continue;
+            }
+        } else if c == '*' || c == '_' {
+            let run = chars[i..].iter().take_while(|&&x| x == c).count();
+            let opens = chars.get(i + run).is_some_and(|x| !x.is_whitespace())
+                && (c == '*' || i == 0 || !chars[i - 1].is_alphanumeric());
+            if opens {
+                if let Some(end) = find_emphasis_close(&chars, i + run, c, run) {
+                    let text: String = chars[i + run..end].iter().collect();
+                    result.push_str(&strip_markdown_inline(&text));
+                    i = end + run;
+                    continue;
+                }
+            }
+            result.extend(&chars[i..i + run]);
+            i += run;
+            continue;
+        }
+        result.push(c);
+        i += 1;
+    }
+    result
+}
+
+/// finds the next occurrence of needle at or after start
+fn find_char(chars: &[char], start: usize, needle: char) -> Option<usize> {
+    chars
+        .get(start..)?
+        .iter()
+        .position(|&c| c == needle)
+        .map(|p| p + start)
+}
+
+/// finds the run of run_length marker characters closing an emphasis that
+/// starts at start
+fn find_emphasis_close(
+    chars: &[char],
+    start: usize,
+    marker: char,
+    run_length: usize,
+) -> Option<usize> {
+    let mut i = start;
+    while i < chars.len() {
+        if chars[i] == marker {
+            let run = chars[i..].iter().take_while(|&&x| x == marker).count();
+            let closes = run == run_length
+                && !chars[i - 1].is_whitespace()
+                && (marker == '*' || chars.get(i + run).is_none_or(|x| !x.is_alphanumeric()));
+            if closes {
+                return Some(i);
+            }
+            i += run;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
 /// findings in advisory in report version 1
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+///
+/// ```
+/// use npm_parser::audit::Finding;
+///
+/// let finding: Finding = serde_json::from_str(
+///     r#"{ "version": "1.2.0", "paths": ["mkdirp>minimist"] }"#,
+/// )?;
+/// assert_eq!(finding.version.as_deref(), Some("1.2.0"));
+/// assert_eq!(finding.paths, vec![vec!["mkdirp", "minimist"]]);
+/// # Ok::<(), serde_json::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Finding {
     /// dependency version found
-    version: String,
+    ///
+    /// optional since npm emits null if it could not resolve the version
+    pub version: Option<String>,
     /// paths from current module to dependency
     #[serde(
         serialize_with = "serialize_module_path_vec",
         deserialize_with = "deserialize_module_path_vec"
     )]
-    paths: Vec<Vec<String>>,
+    pub paths: Vec<Vec<String>>,
 }
 
 /// audit report version 2
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct NpmAuditDataV2 {
     /// version of the audit report
     ///
-    /// not all versions of npm produce this field
+    /// not all versions of npm produce this field, some produce it as a string
+    #[serde(default, deserialize_with = "deserialize_optional_u32_or_string")]
     pub audit_report_version: Option<u32>,
     /// Vulnerabilities found in dependencies
     pub vulnerabilities: BTreeMap<String, VulnerablePackage>,
@@ -242,36 +1177,332 @@ pub struct NpmAuditDataV2 {
     pub metadata: MetadataV2,
 }
 
-/// Actions to perform to fix security issues
-#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, serde::Serialize, serde::Deserialize)]
-#[serde(rename_all = "camelCase", tag = "action")]
-pub enum Action {
-    /// install a new package
-    #[serde(rename_all = "camelCase")]
-    Install {
-        /// which advisories will this action resolve
-        resolves: Vec<Resolves>,
-        /// which package do we need to install
-        module: String,
-        /// how deep in our dependency tree is this package
-        depth: Option<u32>,
-        /// which version of the package do we need to install
-        target: String,
-        /// is this a major version
-        is_major: bool,
-    },
-    /// update a package
-    #[serde(rename_all = "camelCase")]
-    Update {
-        /// which advisories will this action resolve
-        resolves: Vec<Resolves>,
-        /// which package do we need to update
-        module: String,
-        /// how deep in our dependency tree is this package
-        depth: Option<u32>,
-        /// which version of the package do we need to update to
-        target: String,
-    },
+impl NpmAuditDataV2 {
+    /// sorts the `via`, `effects` and `nodes` lists of all vulnerable packages
+    /// into a deterministic order
+    ///
+    /// npm emits these in an arbitrary order which means two serializations
+    /// of equivalent reports can differ. This only reorders the lists, their
+    /// content is preserved.
+    ///
+    /// `via` is sorted by severity (most severe first, name-only entries last)
+    /// and then by name, `effects` and `nodes` are sorted lexicographically.
+    pub fn normalize(&mut self) {
+        for package in self.vulnerabilities.values_mut() {
+            package.via.sort_by(Vulnerability::normalized_order);
+            package.effects.sort();
+            package.nodes.sort();
+        }
+    }
+
+    /// adds the vulnerabilities and counts of another report, e.g. of
+    /// another package in a workspace
+    ///
+    /// if a package is vulnerable in both reports the entry with the higher
+    /// severity is kept (the one already in this report if they are equal)
+    /// and the `via`, `effects` and `nodes` of the other entry are added to
+    /// it without duplicates, since a package can be vulnerable through
+    /// different advisories and be used in different places in each
+    /// workspace.
+    ///
+    /// the vulnerability counts in the metadata are recounted from the
+    /// merged packages so a package in both reports is only counted once,
+    /// the dependency counts of both reports are summed since the reports
+    /// do not say which dependencies they share.
+    pub fn merge(&mut self, other: NpmAuditDataV2) {
+        for (name, package) in other.vulnerabilities {
+            let merged = match self.vulnerabilities.remove(&name) {
+                Some(existing) => existing.merged_with(package),
+                None => package,
+            };
+            self.vulnerabilities.insert(name, merged);
+        }
+        self.recount_vulnerabilities();
+        let dependencies = &mut self.metadata.dependencies;
+        let other_dependencies = &other.metadata.dependencies;
+        dependencies.total += other_dependencies.total;
+        dependencies.prod += other_dependencies.prod;
+        dependencies.dev += other_dependencies.dev;
+        dependencies.optional += other_dependencies.optional;
+        dependencies.peer += other_dependencies.peer;
+        dependencies.peer_optional += other_dependencies.peer_optional;
+        if self.audit_report_version.is_none() {
+            self.audit_report_version = other.audit_report_version;
+        }
+    }
+
+    /// is the given version of the package within the vulnerable range npm
+    /// reported for it
+    ///
+    /// None if the package is not in the report or the range or version can
+    /// not be parsed, see [VersionRange]
+    pub fn is_version_vulnerable(&self, package: &str, version: &str) -> Option<bool> {
+        let package = self.vulnerabilities.get(package)?;
+        VersionRange::parse(&package.range)?.matches_str(version)
+    }
+
+    /// the vulnerabilities mentioning the given CVE in their url or title,
+    /// see [Vulnerability::cve_ids]
+    ///
+    /// matching is case-insensitive and the CVE- prefix is optional, see
+    /// [Cve::parse]. The same vulnerability is returned once for each
+    /// package whose via lists it.
+    pub fn find_by_cve(&self, cve: &str) -> Vec<&Vulnerability> {
+        let Some(cve) = Cve::parse(cve) else {
+            return vec![];
+        };
+        self.vulnerabilities
+            .values()
+            .flat_map(|p| &p.via)
+            .filter(|v| v.cve_ids().contains(&cve))
+            .collect()
+    }
+
+    /// only the vulnerable packages with the given severity or worse, like
+    /// npm's `--audit-level` but applied to the report itself
+    ///
+    /// the vulnerability counts in the metadata are recounted from the
+    /// remaining packages, the dependency counts are kept. Effects and via
+    /// entries naming packages which were dropped are removed as well.
+    pub fn filtered_by_severity(mut self, min: Severity) -> NpmAuditDataV2 {
+        self.vulnerabilities
+            .retain(|_, package| package.severity >= min);
+        let remaining: BTreeSet<String> = self.vulnerabilities.keys().cloned().collect();
+        for package in self.vulnerabilities.values_mut() {
+            package.effects.retain(|effect| remaining.contains(effect));
+            package.via.retain(|vulnerability| match vulnerability {
+                Vulnerability::NameOnly(name) => remaining.contains(name),
+                Vulnerability::Full { .. } | Vulnerability::SourceId(_) => true,
+            });
+        }
+        self.recount_vulnerabilities();
+        self
+    }
+
+    /// sets the vulnerability counts in the metadata to the number of
+    /// vulnerable packages per severity
+    fn recount_vulnerabilities(&mut self) {
+        let mut counts = VulnerabilityCountsV2::default();
+        for package in self.vulnerabilities.values() {
+            let count = match package.severity {
+                Severity::None => continue,
+                Severity::Info => &mut counts.info,
+                Severity::Low => &mut counts.low,
+                Severity::Moderate => &mut counts.moderate,
+                Severity::High => &mut counts.high,
+                Severity::Critical => &mut counts.critical,
+            };
+            *count += 1;
+            counts.total += 1;
+        }
+        self.metadata.vulnerabilities = counts;
+    }
+
+    /// the package versions npm named as fixes, by package name
+    ///
+    /// only fixes with full details name a target, several vulnerable
+    /// packages often share the same fix. If fixes name different versions of
+    /// the same package the highest one is returned since only one version
+    /// can be installed.
+    pub fn upgrade_targets(&self) -> BTreeMap<&str, &str> {
+        let mut targets: BTreeMap<&str, &str> = BTreeMap::new();
+        for package in self.vulnerabilities.values() {
+            if let Fix::Full { name, version, .. } = &package.fix_available {
+                let target = targets.entry(name).or_insert(version);
+                if Versioning::new(version) > Versioning::new(*target) {
+                    *target = version;
+                }
+            }
+        }
+        targets
+    }
+
+    /// the (package, version) pairs of fixes whose target version is not a
+    /// valid semantic version, e.g. a dist-tag like latest
+    ///
+    /// installing `<package>@<version>` with these may not install what the
+    /// fix expects
+    pub fn invalid_fix_targets(&self) -> Vec<(&str, &str)> {
+        self.vulnerabilities
+            .values()
+            .filter_map(|p| match &p.fix_available {
+                Fix::Full { name, version, .. } if SemVer::new(version).is_none() => {
+                    Some((name.as_str(), version.as_str()))
+                }
+                _ => None,
+            })
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// the command line (program and arguments) of a remediation step for
+    /// this report
+    ///
+    /// the heuristic used is
+    ///
+    /// * if no fix is available at all (or only fixes requiring a semver
+    ///   major update and allow_major is false) an empty Vec is returned
+    /// * if no fix requires a semver major update or allow_major is false
+    ///   `npm audit fix` is returned, it only applies non-breaking fixes
+    /// * if allow_major is true and every available fix names its target
+    ///   version the targets from [NpmAuditDataV2::upgrade_targets] are
+    ///   installed explicitly with `npm install <package>@<version>...`
+    /// * otherwise `npm audit fix --force` is returned since npm does not tell
+    ///   us the targets of all fixes
+    pub fn suggested_fix_command(&self, allow_major: bool) -> Vec<String> {
+        let fixes: Vec<&Fix> = self
+            .vulnerabilities
+            .values()
+            .map(|p| &p.fix_available)
+            .filter(|f| f.is_available())
+            .collect();
+        let has_breaking = fixes.iter().any(|f| f.requires_major());
+        let has_non_breaking = fixes.iter().any(|f| !f.requires_major());
+
+        if fixes.is_empty() || (!allow_major && !has_non_breaking) {
+            vec![]
+        } else if !allow_major || !has_breaking {
+            vec!["npm".to_string(), "audit".to_string(), "fix".to_string()]
+        } else if fixes.iter().all(|f| matches!(f, Fix::Full { .. })) {
+            let mut cmd = vec!["npm".to_string(), "install".to_string()];
+            cmd.extend(
+                self.upgrade_targets()
+                    .into_iter()
+                    .map(|(name, version)| format!("{}@{}", name, version)),
+            );
+            cmd
+        } else {
+            vec![
+                "npm".to_string(),
+                "audit".to_string(),
+                "fix".to_string(),
+                "--force".to_string(),
+            ]
+        }
+    }
+
+    /// sorts the vulnerable packages into buckets by how they can be fixed
+    pub fn triage(&self) -> Triage {
+        let mut triage = Triage::default();
+        for (name, package) in &self.vulnerabilities {
+            let fix = &package.fix_available;
+            if !fix.is_available() {
+                triage.investigate.push(name.to_string());
+            } else if fix.requires_major() {
+                triage.plan.push(name.to_string());
+            } else {
+                triage.fix_now.push(name.to_string());
+            }
+        }
+        triage
+    }
+
+    /// the number of vulnerable packages whose fix requires a semver major
+    /// update
+    pub fn breaking_fix_count(&self) -> u32 {
+        self.vulnerabilities
+            .values()
+            .filter(|p| p.fix_available.requires_major())
+            .count() as u32
+    }
+
+    /// the number of vulnerable packages with a fix which does not require a
+    /// semver major update, i.e. what `npm audit fix` can fix without
+    /// --force
+    ///
+    /// fixes without details count as safe since npm does not say otherwise
+    pub fn safely_fixable_count(&self) -> u32 {
+        self.vulnerabilities
+            .values()
+            .filter(|p| p.fix_available.is_available() && !p.fix_available.requires_major())
+            .count() as u32
+    }
+
+    /// the vulnerable packages matching the predicate, sorted by name
+    ///
+    /// ```
+    /// use npm_parser::audit::{NpmAuditDataV2, Severity};
+    ///
+    /// let data: NpmAuditDataV2 = serde_json::from_str(r#"{
+    ///   "vulnerabilities": {
+    ///     "lodash": {
+    ///       "name": "lodash", "severity": "critical", "isDirect": true,
+    ///       "via": [], "effects": [], "range": "<4.17.21",
+    ///       "nodes": ["node_modules/lodash"], "fixAvailable": true
+    ///     },
+    ///     "minimist": {
+    ///       "name": "minimist", "severity": "critical", "isDirect": false,
+    ///       "via": [], "effects": [], "range": "<0.2.1",
+    ///       "nodes": ["node_modules/minimist"], "fixAvailable": true
+    ///     },
+    ///     "optimist": {
+    ///       "name": "optimist", "severity": "low", "isDirect": true,
+    ///       "via": [], "effects": [], "range": ">=0.6.0",
+    ///       "nodes": ["node_modules/optimist"], "fixAvailable": false
+    ///     }
+    ///   },
+    ///   "metadata": {
+    ///     "vulnerabilities": {"info": 0, "low": 1, "moderate": 0, "high": 0, "critical": 2, "total": 3},
+    ///     "dependencies": {"prod": 3, "dev": 0, "optional": 0, "peer": 0, "peerOptional": 0, "total": 3}
+    ///   }
+    /// }"#).unwrap();
+    ///
+    /// // high or critical direct dependencies with a fix
+    /// let urgent = data.filter(|p| {
+    ///     p.severity >= Severity::High && p.is_direct && p.fix_available.is_available()
+    /// });
+    /// assert_eq!(urgent.len(), 1);
+    /// assert_eq!(urgent[0].name, "lodash");
+    /// ```
+    pub fn filter<F: Fn(&VulnerablePackage) -> bool>(&self, pred: F) -> Vec<&VulnerablePackage> {
+        self.vulnerabilities.values().filter(|p| pred(p)).collect()
+    }
+}
+
+/// The vulnerable packages of a report sorted by how they can be fixed
+///
+/// returned by [NpmAuditDataV2::triage], all lists contain package names
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Triage {
+    /// a fix without a semver major update is available
+    pub fix_now: Vec<String>,
+    /// a fix is available but requires a semver major update
+    pub plan: Vec<String>,
+    /// no fix is available
+    pub investigate: Vec<String>,
+}
+
+/// Actions to perform to fix security issues
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", tag = "action")]
+pub enum Action {
+    /// install a new package
+    #[serde(rename_all = "camelCase")]
+    Install {
+        /// which advisories will this action resolve
+        resolves: Vec<Resolves>,
+        /// which package do we need to install
+        module: String,
+        /// how deep in our dependency tree is this package
+        depth: Option<u32>,
+        /// which version of the package do we need to install
+        target: String,
+        /// is this a major version
+        is_major: bool,
+    },
+    /// update a package
+    #[serde(rename_all = "camelCase")]
+    Update {
+        /// which advisories will this action resolve
+        resolves: Vec<Resolves>,
+        /// which package do we need to update
+        module: String,
+        /// how deep in our dependency tree is this package
+        depth: Option<u32>,
+        /// which version of the package do we need to update to
+        target: String,
+    },
     /// review code using a package
     #[serde(rename_all = "camelCase")]
     Review {
@@ -284,8 +1515,23 @@ pub enum Action {
     },
 }
 
+/// A change to a package recommended by npm in report version 1
+///
+/// returned by [NpmAuditDataV1::remediation_plan]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PlannedChange {
+    /// the package to install or update
+    pub module: String,
+    /// the version to install or update to
+    pub target: String,
+    /// is this a semver major update, always false for update actions
+    pub is_major: bool,
+    /// the ids of the advisories resolved by this change, sorted
+    pub advisory_ids: Vec<u64>,
+}
+
 /// Which advisories are resolved by an action
-#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Resolves {
     /// advisory id
@@ -305,7 +1551,9 @@ pub struct Resolves {
 }
 
 /// Severity of vulnerabilities
-#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, serde::Serialize, serde::Deserialize)]
+#[derive(
+    Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, serde::Serialize, serde::Deserialize,
+)]
 #[serde(rename_all = "camelCase")]
 pub enum Severity {
     /// no need to take action
@@ -322,8 +1570,45 @@ pub enum Severity {
     Critical,
 }
 
+impl Severity {
+    /// the name npm uses for this severity
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::None => "none",
+            Severity::Info => "info",
+            Severity::Low => "low",
+            Severity::Moderate => "moderate",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for Severity {
+    type Err = crate::Error;
+
+    /// parses the names npm uses, see [Severity::as_str]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Severity::None),
+            "info" => Ok(Severity::Info),
+            "low" => Ok(Severity::Low),
+            "moderate" => Ok(Severity::Moderate),
+            "high" => Ok(Severity::High),
+            "critical" => Ok(Severity::Critical),
+            _ => Err(crate::Error::UnknownSeverity(s.to_string())),
+        }
+    }
+}
+
 /// The details for a single vulnerable package
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct VulnerablePackage {
     /// Package name
@@ -344,8 +1629,52 @@ pub struct VulnerablePackage {
     pub fix_available: Fix,
 }
 
+impl VulnerablePackage {
+    /// combines two entries for the same package, see [NpmAuditDataV2::merge]
+    fn merged_with(self, other: VulnerablePackage) -> VulnerablePackage {
+        let (mut kept, added) = if other.severity > self.severity {
+            (other, self)
+        } else {
+            (self, other)
+        };
+        kept.is_direct |= added.is_direct;
+        for via in added.via {
+            if !kept.via.contains(&via) {
+                kept.via.push(via);
+            }
+        }
+        for effect in added.effects {
+            if !kept.effects.contains(&effect) {
+                kept.effects.push(effect);
+            }
+        }
+        for node in added.nodes {
+            if !kept.nodes.contains(&node) {
+                kept.nodes.push(node);
+            }
+        }
+        kept
+    }
+
+    /// is the version npm suggests as a fix within the given semver range
+    ///
+    /// the declared range has to come from the caller, usually from the
+    /// package.json entry for the package named in the fix (which is not
+    /// always the vulnerable package itself). Some(true) means npm update
+    /// suffices, Some(false) means the package.json has to be edited.
+    ///
+    /// returns None if no fix target is known or the range or target can
+    /// not be parsed
+    pub fn fix_within_range(&self, declared_range: &str) -> Option<bool> {
+        match &self.fix_available {
+            Fix::Full { version, .. } => VersionRange::parse(declared_range)?.matches_str(version),
+            Fix::BoolOnly(_) => None,
+        }
+    }
+}
+
 /// a single vulnerability
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase", untagged)]
 pub enum Vulnerability {
     /// some vulnerabilities in the via list are only a name
@@ -366,11 +1695,96 @@ pub enum Vulnerability {
         severity: Severity,
         /// the affected version range
         range: String,
+        /// the CVSS score of the vulnerability, not included by all npm
+        /// versions
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cvss: Option<Cvss>,
     },
+    /// a few npm builds emit only the numeric source id of the advisory
+    SourceId(u64),
+}
+
+/// A CVSS (common vulnerability scoring system) score
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Cvss {
+    /// the score between 0.0 and 10.0
+    pub score: f32,
+    /// the CVSS vector the score was calculated from, e.g.
+    /// `CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:N/I:N/A:H`
+    pub vector_string: Option<String>,
+}
+
+impl Vulnerability {
+    /// the CVSS score of entries with full details if npm included it
+    pub fn cvss(&self) -> Option<&Cvss> {
+        match self {
+            Vulnerability::Full { cvss, .. } => cvss.as_ref(),
+            Vulnerability::NameOnly(_) | Vulnerability::SourceId(_) => None,
+        }
+    }
+
+    /// the name of the vulnerability or package this entry refers to
+    ///
+    /// empty for entries which only contain a source id
+    pub fn name(&self) -> &str {
+        match self {
+            Vulnerability::NameOnly(name) => name,
+            Vulnerability::Full { name, .. } => name,
+            Vulnerability::SourceId(_) => "",
+        }
+    }
+
+    /// the severity of this vulnerability, only available for entries with
+    /// full details
+    pub fn severity(&self) -> Option<&Severity> {
+        match self {
+            Vulnerability::NameOnly(_) | Vulnerability::SourceId(_) => None,
+            Vulnerability::Full { severity, .. } => Some(severity),
+        }
+    }
+
+    /// the CVE identifiers mentioned in the url or title of this
+    /// vulnerability, only available for entries with full details
+    pub fn cve_ids(&self) -> Vec<Cve> {
+        match self {
+            Vulnerability::NameOnly(_) | Vulnerability::SourceId(_) => vec![],
+            Vulnerability::Full { url, title, .. } => {
+                let mut cves = extract_cves(url);
+                cves.extend(extract_cves(title));
+                cves
+            }
+        }
+    }
+
+    /// the order used by [NpmAuditDataV2::normalize]
+    fn normalized_order(a: &Self, b: &Self) -> std::cmp::Ordering {
+        b.severity()
+            .cmp(&a.severity())
+            .then_with(|| a.name().cmp(b.name()))
+            .then_with(|| match (a, b) {
+                (
+                    Vulnerability::Full {
+                        source: source_a,
+                        url: url_a,
+                        ..
+                    },
+                    Vulnerability::Full {
+                        source: source_b,
+                        url: url_b,
+                        ..
+                    },
+                ) => source_a.cmp(source_b).then_with(|| url_a.cmp(url_b)),
+                (Vulnerability::SourceId(source_a), Vulnerability::SourceId(source_b)) => {
+                    source_a.cmp(source_b)
+                }
+                _ => std::cmp::Ordering::Equal,
+            })
+    }
 }
 
 /// a single fix
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(untagged)]
 pub enum Fix {
     /// some packages only indicate whether a fix is available or not
@@ -383,13 +1797,38 @@ pub enum Fix {
         /// the fixed package version
         version: String,
         /// is this a semver major update
+        ///
+        /// some npm builds spell this isSemverMajor
+        #[serde(alias = "isSemverMajor")]
         is_sem_ver_major: bool,
     },
 }
 
+impl Fix {
+    /// is any fix available
+    pub fn is_available(&self) -> bool {
+        match self {
+            Fix::BoolOnly(available) => *available,
+            Fix::Full { .. } => true,
+        }
+    }
+
+    /// does the fix require a semver major update
+    ///
+    /// this is only known for fixes with full details
+    pub fn requires_major(&self) -> bool {
+        match self {
+            Fix::BoolOnly(_) => false,
+            Fix::Full {
+                is_sem_ver_major, ..
+            } => *is_sem_ver_major,
+        }
+    }
+}
+
 /// The vulnerability and dependency counts returned by npm-audit in report
 /// version 1
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MetadataV1 {
     /// Vulnerability counts (without total)
@@ -406,7 +1845,7 @@ pub struct MetadataV1 {
 
 /// The vulnerability and dependency counts returned by npm-audit in report
 /// version 2
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MetadataV2 {
     /// Vulnerability counts
@@ -417,7 +1856,7 @@ pub struct MetadataV2 {
 
 /// The vulnerability and dependency counts returned by npm-audit in report
 /// version 1
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct VulnerabilityCountsV1 {
     /// Number of info level vulnerabilities
     pub info: u32,
@@ -433,7 +1872,11 @@ pub struct VulnerabilityCountsV1 {
 
 /// The vulnerability and dependency counts returned by npm-audit in report
 /// version 2
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+///
+/// some npm 7 builds do not include the total, it is computed from the other
+/// counts then
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(from = "RawVulnerabilityCountsV2")]
 pub struct VulnerabilityCountsV2 {
     /// Number of total vulnerabilities
     pub total: u32,
@@ -449,8 +1892,42 @@ pub struct VulnerabilityCountsV2 {
     pub critical: u32,
 }
 
+/// [VulnerabilityCountsV2] as npm emits it, with an optional total
+#[derive(serde::Deserialize)]
+struct RawVulnerabilityCountsV2 {
+    /// Number of total vulnerabilities, missing in some npm 7 builds
+    total: Option<u32>,
+    /// Number of info level vulnerabilities
+    info: u32,
+    /// Number of low level vulnerabilities
+    low: u32,
+    /// Number of moderate level vulnerabilities
+    moderate: u32,
+    /// Number of high level vulnerabilities
+    high: u32,
+    /// Number of critical level vulnerabilities
+    critical: u32,
+}
+
+impl From<RawVulnerabilityCountsV2> for VulnerabilityCountsV2 {
+    fn from(raw: RawVulnerabilityCountsV2) -> Self {
+        Self {
+            total: raw.total.unwrap_or_else(|| {
+                [raw.low, raw.moderate, raw.high, raw.critical]
+                    .into_iter()
+                    .fold(raw.info, u32::saturating_add)
+            }),
+            info: raw.info,
+            low: raw.low,
+            moderate: raw.moderate,
+            high: raw.high,
+            critical: raw.critical,
+        }
+    }
+}
+
 /// The vulnerability and dependency counts returned by npm-audit
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DependencyCounts {
     /// Total number of dependencies
@@ -469,8 +1946,56 @@ pub struct DependencyCounts {
     pub peer_optional: u32,
 }
 
+/// The metadata (vulnerability and dependency counts) of either report
+/// version
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum AuditMetadata {
+    /// metadata of audit report version 1
+    Version1(MetadataV1),
+    /// metadata of audit report version 2
+    Version2(MetadataV2),
+}
+
+/// merges all reports into one, see [NpmAuditDataV2::merge]
+///
+/// returns an empty report if there are none
+pub fn merge_all(reports: impl IntoIterator<Item = NpmAuditDataV2>) -> NpmAuditDataV2 {
+    let mut merged = NpmAuditDataV2::default();
+    for report in reports {
+        merged.merge(report);
+    }
+    merged
+}
+
+/// parses only the metadata of an npm-audit report
+///
+/// the vulnerabilities and advisories are skipped without allocating them
+/// which is a lot faster for huge reports if only the counts are needed.
+/// The report version is determined from the shape of the metadata.
+pub fn parse_audit_metadata_only(json: &str) -> Result<AuditMetadata, crate::Error> {
+    /// the only part of the report we are interested in
+    #[derive(Deserialize)]
+    struct MetadataOnly {
+        /// the metadata of either report version
+        metadata: serde_json::Value,
+    }
+
+    let jd = &mut serde_json::Deserializer::from_str(json);
+    let MetadataOnly { metadata } = serde_path_to_error::deserialize(jd)?;
+    if metadata.get("dependencies").is_some_and(|d| d.is_object()) {
+        Ok(AuditMetadata::Version2(serde_path_to_error::deserialize(
+            metadata,
+        )?))
+    } else {
+        Ok(AuditMetadata::Version1(serde_path_to_error::deserialize(
+            metadata,
+        )?))
+    }
+}
+
 /// What the exit code indicated about required updates
-#[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum IndicatedUpdateRequirement {
     /// No update is required
     UpToDate,
@@ -491,22 +2016,18 @@ impl std::fmt::Display for IndicatedUpdateRequirement {
     }
 }
 
-/// main entry point for the npm-audit call
-pub fn audit() -> Result<(IndicatedUpdateRequirement, NpmAuditData), crate::Error> {
-    let mut version_cmd = Command::new("npm");
-
-    version_cmd.args(["--version"]);
+/// The first npm version which produces audit report version 2, all versions
+/// before this one produce report version 1
+pub const AUDIT_REPORT_V2_MIN_NPM_VERSION: &str = "7.0.0";
 
-    let version_output = version_cmd.output()?;
-
-    let version = from_utf8(&version_output.stdout)?.trim();
-
-    debug!("Got version string {} from npm --version", version);
-
-    let report_format = match versions::Versioning::new(version) {
+/// determines the audit report format npm will produce from the output of
+/// npm --version
+pub fn report_format_for_npm_version(version: &str) -> u8 {
+    match versions::Versioning::new(version.trim()) {
         Some(version) => {
             debug!("Got version {} from npm --version", version);
-            let audit_report_change = versions::Versioning::new("7.0.0").unwrap();
+            let audit_report_change =
+                versions::Versioning::new(AUDIT_REPORT_V2_MIN_NPM_VERSION).unwrap();
             if version < audit_report_change {
                 debug!(
                     "Dealing with npm before version {}, using report format 1",
@@ -528,56 +2049,3295 @@ pub fn audit() -> Result<(IndicatedUpdateRequirement, NpmAuditData), crate::Erro
             debug!("Could not parse npm version, defaulting to report format 2");
             2
         }
-    };
-    debug!("Using report format {}", report_format);
+    }
+}
+
+/// determines the audit report format from the content of the npm output
+///
+/// an auditReportVersion field is used if present, otherwise the structure
+/// decides: a vulnerabilities map with via lists or a total in the
+/// vulnerability counts means report version 2 (early npm 7 releases do not
+/// include auditReportVersion), advisories or actions mean report version 1.
+///
+/// returns None if the content is no JSON object or matches neither
+pub fn detect_report_format(json_str: &str) -> Option<u8> {
+    detect_report_format_value(&serde_json::from_str(json_str).ok()?)
+}
 
-    let mut cmd = Command::new("npm");
+/// like [detect_report_format] but for JSON which was already parsed, e.g.
+/// the value returned by [parse_audit_with_value]
+pub fn detect_report_format_value(value: &serde_json::Value) -> Option<u8> {
+    let object = value.as_object()?;
+    match object.get("auditReportVersion") {
+        Some(serde_json::Value::Number(n)) => return n.as_u64().and_then(|n| n.try_into().ok()),
+        Some(serde_json::Value::String(s)) => return s.trim().parse().ok(),
+        _ => {}
+    }
+    let has_total = object
+        .get("metadata")
+        .and_then(|m| m.get("vulnerabilities"))
+        .and_then(|v| v.get("total"))
+        .is_some();
+    let has_via = object
+        .get("vulnerabilities")
+        .and_then(|v| v.as_object())
+        .is_some_and(|v| v.values().any(|p| p.get("via").is_some()));
+    if has_total || has_via {
+        Some(2)
+    } else if object.contains_key("advisories") || object.contains_key("actions") {
+        Some(1)
+    } else {
+        None
+    }
+}
 
-    cmd.args(["audit", "--json"]);
+/// A group of dependencies which can be omitted from or included in an
+/// audit
+///
+/// maps to the values of npm's `--omit` and `--include` flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DependencyGroup {
+    /// development dependencies (devDependencies in package.json)
+    Dev,
+    /// optional dependencies (optionalDependencies in package.json)
+    Optional,
+    /// peer dependencies (peerDependencies in package.json)
+    Peer,
+}
 
-    let output = cmd.output()?;
+/// The dependency groups which can be passed to [AuditCommand::omit]
+pub type OmitKind = DependencyGroup;
 
-    if !output.status.success() {
-        warn!(
-            "npm audit did not return with a successful exit code: {}",
-            output.status
-        );
-        debug!("stdout:\n{}", from_utf8(&output.stdout)?);
-        if !output.stderr.is_empty() {
-            warn!("stderr:\n{}", from_utf8(&output.stderr)?);
+impl std::fmt::Display for DependencyGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DependencyGroup::Dev => {
+                write!(f, "dev")
+            }
+            DependencyGroup::Optional => {
+                write!(f, "optional")
+            }
+            DependencyGroup::Peer => {
+                write!(f, "peer")
+            }
         }
     }
+}
 
-    let update_requirement = if output.status.success() {
-        IndicatedUpdateRequirement::UpToDate
-    } else {
-        IndicatedUpdateRequirement::UpdateRequired
-    };
+/// Where npm is and where it is run
+///
+/// used by [AuditCommand::options], [audit_with_options] and the equivalents
+/// for npm-outdated
+#[derive(Debug, Default, Clone)]
+pub struct AuditOptions {
+    /// the npm binary to run, None to look up npm in the PATH
+    pub npm_path: Option<PathBuf>,
+    /// the directory to run npm in, None for the current directory
+    pub working_dir: Option<PathBuf>,
+    /// how long npm may run before it is killed and [crate::Error::Timeout]
+    /// is returned, None to wait as long as it takes
+    pub timeout: Option<Duration>,
+    /// dependency groups passed to npm-audit as `--omit=<group>` in addition
+    /// to those from [AuditCommand::omit], npm-outdated ignores this
+    pub omit: Vec<OmitKind>,
+}
 
-    let json_str = from_utf8(&output.stdout)?;
-    let jd = &mut serde_json::Deserializer::from_str(json_str);
-    let data: NpmAuditData = match report_format {
-        1 => NpmAuditData::Version1(serde_path_to_error::deserialize::<_, NpmAuditDataV1>(jd)?),
-        2 => NpmAuditData::Version2(serde_path_to_error::deserialize::<_, NpmAuditDataV2>(jd)?),
-        _ => {
-            panic!("Unknown report version")
-        }
-    };
-    Ok((update_requirement, data))
+impl AuditOptions {
+    /// the npm binary to run
+    pub(crate) fn npm(&self) -> &Path {
+        self.npm_path.as_deref().unwrap_or(Path::new("npm"))
+    }
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use crate::Error;
-    use tracing_test::traced_test;
+/// Builder for a call to npm-audit
+///
+/// [audit] is a shortcut for running this with the default settings
+#[derive(Debug, Default, Clone)]
+pub struct AuditCommand {
+    /// the npm binary and working directory
+    options: AuditOptions,
+    /// dependency groups passed to npm as `--omit=<group>`
+    omit: Vec<DependencyGroup>,
+    /// dependency groups passed to npm as `--include=<group>`
+    include: Vec<DependencyGroup>,
+    /// measure how long the npm call and the parsing took
+    timed: bool,
+    /// replace invalid UTF-8 in the npm output instead of failing
+    lossy_utf8: bool,
+    /// the lockfile of the project to audit
+    lockfile: Option<PathBuf>,
+    /// pass --legacy-peer-deps to npm
+    legacy_peer_deps: bool,
+    /// return an error instead of auditing only the lockfile if there is no
+    /// node_modules directory
+    require_node_modules: bool,
+    /// the npm cache directory passed as `--cache <path>`
+    cache_dir: Option<PathBuf>,
+    /// the npm version to use instead of probing it
+    npm_version: Option<NpmVersion>,
+    /// the report format to parse regardless of the npm version
+    report_format: Option<u8>,
+    /// pass --offline to npm
+    offline: bool,
+    /// run npm audit fix --dry-run --force instead of npm audit
+    dry_run_force: bool,
+    /// additional npm config passed as `--<key>=<value>`
+    config: Vec<(String, String)>,
+    /// the node binary and the npm cli.js to run with it instead of npm
+    via_node: Option<(PathBuf, PathBuf)>,
+    /// the data written to the stdin of npm
+    stdin: Option<Vec<u8>>,
+    /// the lowest severity which makes an update required
+    actionable_floor: Option<Severity>,
+    /// the severity passed to npm as `--audit-level=<severity>` and below
+    /// which vulnerabilities are dropped from the report
+    audit_level: Option<Severity>,
+    /// keep the unparsed output of npm in the result
+    keep_raw: bool,
+    /// the package tarball to audit
+    #[cfg(feature = "tarball")]
+    tarball: Option<PathBuf>,
+}
 
-    /// this test requires a package.json and package-lock.json in the main crate
-    /// directory (working dir of the tests)
-    #[traced_test]
-    #[test]
-    fn test_run_npm_audit() -> Result<(), Error> {
-        audit()?;
+impl AuditCommand {
+    /// creates a builder with the default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// use the npm binary and working directory from the options
+    ///
+    /// the directory of [AuditCommand::lockfile] takes precedence over the
+    /// working directory
+    pub fn options(mut self, options: AuditOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// omit the given dependency groups from the audit (`--omit=<group>`)
+    pub fn omit(mut self, groups: &[DependencyGroup]) -> Self {
+        for group in groups {
+            if !self.omit.contains(group) {
+                self.omit.push(*group);
+            }
+        }
+        self
+    }
+
+    /// include the given dependency groups in the audit (`--include=<group>`)
+    ///
+    /// this is mostly useful to override an omit configured in .npmrc
+    pub fn include(mut self, groups: &[DependencyGroup]) -> Self {
+        for group in groups {
+            if !self.include.contains(group) {
+                self.include.push(*group);
+            }
+        }
+        self
+    }
+
+    /// measure how long the npm call and the parsing of its output took
+    ///
+    /// the measurements are returned in [AuditResult::timings]
+    pub fn timed(mut self, timed: bool) -> Self {
+        self.timed = timed;
+        self
+    }
+
+    /// replace invalid UTF-8 sequences in the npm output with the unicode
+    /// replacement character instead of returning an error
+    ///
+    /// by default invalid UTF-8 is an error
+    pub fn lossy_utf8(mut self, lossy_utf8: bool) -> Self {
+        self.lossy_utf8 = lossy_utf8;
+        self
+    }
+
+    /// audit the project the given lockfile belongs to
+    ///
+    /// npm does not accept a lockfile path so it is run in the directory
+    /// containing the lockfile instead. The file has to exist and be named
+    /// package-lock.json or npm-shrinkwrap.json, this is checked when the
+    /// command is run.
+    pub fn lockfile(mut self, path: impl Into<PathBuf>) -> Self {
+        self.lockfile = Some(path.into());
+        self
+    }
+
+    /// pass --legacy-peer-deps to npm
+    ///
+    /// this makes npm ignore peer dependencies when building the dependency
+    /// tree, the same way npm 6 did, which avoids failures on projects with
+    /// peer dependency conflicts. Since it changes how the tree is resolved
+    /// it may change which vulnerabilities are reported.
+    pub fn legacy_peer_deps(mut self, legacy_peer_deps: bool) -> Self {
+        self.legacy_peer_deps = legacy_peer_deps;
+        self
+    }
+
+    /// what to do if the project has no node_modules directory
+    ///
+    /// by default npm is told to audit only the lockfile
+    /// (`--package-lock-only`) in that case, if this is set
+    /// [crate::Error::NodeModulesMissing] is returned instead
+    pub fn require_node_modules(mut self, require_node_modules: bool) -> Self {
+        self.require_node_modules = require_node_modules;
+        self
+    }
+
+    /// use the given directory as the npm cache (`--cache <path>`)
+    ///
+    /// this isolates the audit from the global cache of the user which avoids
+    /// polluting it and permission errors e.g. in containers or CI. Note that
+    /// npm has to fill a fresh cache first so the first run is slower.
+    pub fn cache_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(path.into());
+        self
+    }
+
+    /// use the given npm version instead of running `npm --version` to
+    /// determine the report format
+    ///
+    /// this saves a call to npm per audit when auditing many projects with the
+    /// same npm. If the version is not the one of the npm actually run the
+    /// wrong report format is selected and parsing fails.
+    pub fn npm_version(mut self, version: versions::Versioning) -> Self {
+        self.npm_version = Some(NpmVersion::from_versioning(version));
+        self
+    }
+
+    /// parse the output in the given report format (1 or 2) regardless of the
+    /// npm version
+    ///
+    /// this also skips the `npm --version` call
+    pub fn force_report_format(mut self, report_format: u8) -> Self {
+        self.report_format = Some(report_format);
+        self
+    }
+
+    /// pass --offline to npm so it only uses its cache instead of the registry
+    ///
+    /// if the cache does not contain everything needed for the audit
+    /// [crate::Error::NetworkRequired] is returned, this allows air-gapped
+    /// pipelines to fail fast instead of waiting for a registry which can
+    /// not be reached
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// run `npm audit fix --dry-run --force` instead of `npm audit`
+    ///
+    /// --force allows fixes with semver major updates so this previews the
+    /// maximal remediation without changing anything. The counts of changed
+    /// packages are returned in [AuditResult::fix_preview], the data is the
+    /// audit report npm includes in the output. This needs npm 7 or later.
+    pub fn dry_run_force(mut self, dry_run_force: bool) -> Self {
+        self.dry_run_force = dry_run_force;
+        self
+    }
+
+    /// pass an arbitrary npm config to npm as `--<key>=<value>`
+    ///
+    /// this is an escape hatch for settings without a dedicated setter, e.g.
+    /// `config("strict-ssl", "false")`. The key is given without the leading
+    /// dashes, a key that is empty or contains whitespace or an equals sign
+    /// makes [AuditCommand::args] return [crate::Error::InvalidConfigKey]
+    pub fn config(mut self, key: &str, value: &str) -> Self {
+        self.config.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// run npm as `<node> <npm_cli_js> audit ...` instead of running the npm
+    /// binary
+    ///
+    /// this is for environments where only a node binary and the path of
+    /// npm's bin/npm-cli.js are known, e.g. to use the npm belonging to a
+    /// specific node version. It takes precedence over
+    /// [AuditOptions::npm_path] and is also used to probe the npm version.
+    pub fn via_node(mut self, node: PathBuf, npm_cli_js: PathBuf) -> Self {
+        self.via_node = Some((node, npm_cli_js));
+        self
+    }
+
+    /// write the given data to the stdin of the npm audit call
+    ///
+    /// this is for npm wrappers which read e.g. credentials from stdin. The
+    /// data is written on a separate thread while the output is read so a
+    /// wrapper which does not read all of it or writes output first can not
+    /// deadlock the call. Without this stdin is closed so nothing can wait
+    /// for input.
+    pub fn stdin(mut self, data: Vec<u8>) -> Self {
+        self.stdin = Some(data);
+        self
+    }
+
+    /// only require an update if there is a vulnerability with the given
+    /// severity or worse, e.g. to ignore info and low severity findings
+    ///
+    /// this re-derives [AuditResult::requirement] from the parsed report
+    /// metadata (see [NpmAuditData::count_at_least]) instead of using the
+    /// exit code of npm. Unlike `--audit-level` it is not passed to npm so
+    /// the report still contains all vulnerabilities.
+    pub fn actionable_floor(mut self, floor: Severity) -> Self {
+        self.actionable_floor = Some(floor);
+        self
+    }
+
+    /// pass `--audit-level=<level>` to npm so only vulnerabilities with the
+    /// given severity or worse make it exit with an error, and drop those
+    /// below it from the report with [NpmAuditDataV2::filtered_by_severity]
+    ///
+    /// the npm names of the levels (none, info, low, moderate, high and
+    /// critical) can be parsed into a [Severity]. Version 1 reports are kept
+    /// as npm printed them.
+    pub fn audit_level(mut self, level: Severity) -> Self {
+        self.audit_level = Some(level);
+        self
+    }
+
+    /// keep the unparsed output of npm in [AuditResult::raw], e.g. to store
+    /// it or to read fields this crate does not parse
+    pub fn keep_raw(mut self, keep_raw: bool) -> Self {
+        self.keep_raw = keep_raw;
+        self
+    }
+
+    /// a command running npm without any arguments for npm yet
+    fn npm_command(&self) -> Command {
+        match &self.via_node {
+            Some((node, npm_cli_js)) => {
+                let mut cmd = Command::new(node);
+                cmd.arg(npm_cli_js);
+                cmd
+            }
+            None => Command::new(self.options.npm()),
+        }
+    }
+
+    /// the report format used to parse the npm output with the given npm
+    /// version, see [AuditCommand::probed_npm_version]
+    ///
+    /// None if the npm version could not be parsed, the format has to be
+    /// detected from the output then
+    fn report_format_for(&self, version: Option<&NpmVersion>) -> Option<u8> {
+        self.report_format
+            .or_else(|| version.and_then(version_report_format))
+    }
+
+    /// the configured npm version, or if neither the version nor the report
+    /// format were configured the probed one
+    fn probed_npm_version(&self) -> Result<Option<NpmVersion>, crate::Error> {
+        if let Some(version) = &self.npm_version {
+            return Ok(Some(version.clone()));
+        }
+        if self.report_format.is_some() {
+            return Ok(None);
+        }
+        probe_npm_version_with(self.version_command()?, self.options.timeout).map(Some)
+    }
+
+    /// the npm command for the version probe, run in the same directory as
+    /// the audit call since e.g. a .npmrc or corepack there can select a
+    /// different npm
+    fn version_command(&self) -> Result<Command, crate::Error> {
+        let mut cmd = self.npm_command();
+        if let Some(dir) = self.current_dir()? {
+            cmd.current_dir(dir);
+        }
+        Ok(cmd)
+    }
+
+    /// like [AuditCommand::probed_npm_version] but probes the npm version
+    /// without blocking the async executor
+    #[cfg(feature = "tokio")]
+    async fn probed_npm_version_async(&self) -> Result<Option<NpmVersion>, crate::Error> {
+        if let Some(version) = &self.npm_version {
+            return Ok(Some(version.clone()));
+        }
+        if self.report_format.is_some() {
+            return Ok(None);
+        }
+        crate::version::probe_npm_version_with_async(self.version_command()?, self.options.timeout)
+            .await
+            .map(Some)
+    }
+
+    /// audit a package tarball (.tgz as produced by npm pack) which is not
+    /// installed
+    ///
+    /// the tarball is extracted to a temporary directory, a lockfile is
+    /// created there with `npm install --package-lock-only --ignore-scripts`
+    /// (which needs access to the registry) and then that lockfile is
+    /// audited. The temporary directory is removed afterwards.
+    ///
+    /// this overrides [AuditCommand::lockfile] and requires the tarball
+    /// feature which adds dependencies on the tar and flate2 crates
+    #[cfg(feature = "tarball")]
+    pub fn tarball(mut self, path: impl Into<PathBuf>) -> Self {
+        self.tarball = Some(path.into());
+        self
+    }
+
+    /// runs the audit on the configured tarball
+    #[cfg(feature = "tarball")]
+    fn run_tarball(&self, tarball: &Path) -> Result<AuditResult, crate::Error> {
+        let temp_dir = crate::tarball::TempDir::new()?;
+        let package_dir = crate::tarball::extract_tarball(tarball, temp_dir.path())?;
+        debug!(
+            "Extracted {} to {}",
+            tarball.display(),
+            package_dir.display()
+        );
+
+        let mut lock_cmd = self.lock_command(&package_dir);
+        let lock_output = crate::process::output(&mut lock_cmd, None, self.options.timeout)?;
+        self.check_lock_output(&lock_output)?;
+        self.for_extracted_tarball(&package_dir).run()
+    }
+
+    /// like [AuditCommand::run_tarball] but without blocking the async
+    /// executor while npm runs
+    #[cfg(all(feature = "tarball", feature = "tokio"))]
+    async fn run_tarball_async(&self, tarball: &Path) -> Result<AuditResult, crate::Error> {
+        let temp_dir = crate::tarball::TempDir::new()?;
+        let package_dir = crate::tarball::extract_tarball(tarball, temp_dir.path())?;
+        debug!(
+            "Extracted {} to {}",
+            tarball.display(),
+            package_dir.display()
+        );
+
+        let lock_cmd = self.lock_command(&package_dir);
+        let lock_output =
+            crate::process::output_async(lock_cmd, None, self.options.timeout).await?;
+        self.check_lock_output(&lock_output)?;
+        self.for_extracted_tarball(&package_dir)
+            .run_npm_async()
+            .await
+    }
+
+    /// the npm call creating the lockfile for an extracted tarball
+    #[cfg(feature = "tarball")]
+    fn lock_command(&self, package_dir: &Path) -> Command {
+        let mut lock_cmd = self.npm_command();
+        lock_cmd.args(["install", "--package-lock-only", "--ignore-scripts"]);
+        if let Some(cache_dir) = &self.cache_dir {
+            lock_cmd.arg("--cache").arg(cache_dir);
+        }
+        lock_cmd.current_dir(package_dir);
+        lock_cmd
+    }
+
+    /// checks the npm call creating the lockfile for a tarball
+    ///
+    /// returns [crate::Error::NpmCommandFailed] with what npm printed on
+    /// stderr if it failed, the audit would only fail later without the
+    /// reason otherwise
+    #[cfg(feature = "tarball")]
+    fn check_lock_output(&self, lock_output: &std::process::Output) -> Result<(), crate::Error> {
+        if lock_output.status.success() {
+            return Ok(());
+        }
+        warn!(
+            "npm install --package-lock-only did not return with a successful exit code: {}",
+            lock_output.status
+        );
+        Err(crate::Error::NpmCommandFailed {
+            status: lock_output.status.code(),
+            stderr: decode_output(&lock_output.stderr, true)?.into_owned(),
+        })
+    }
+
+    /// these settings for auditing the lockfile created for an extracted
+    /// tarball
+    #[cfg(feature = "tarball")]
+    fn for_extracted_tarball(&self, package_dir: &Path) -> AuditCommand {
+        let mut inner = self.clone();
+        inner.tarball = None;
+        inner.lockfile = Some(package_dir.join("package-lock.json"));
+        inner
+    }
+
+    /// the directory npm is run in, None for the current directory
+    fn current_dir(&self) -> Result<Option<&Path>, crate::Error> {
+        let Some(lockfile) = &self.lockfile else {
+            return Ok(self.options.working_dir.as_deref());
+        };
+        let valid_name = matches!(
+            lockfile.file_name().and_then(|n| n.to_str()),
+            Some("package-lock.json") | Some("npm-shrinkwrap.json")
+        );
+        if !valid_name || !lockfile.is_file() {
+            return Err(crate::Error::InvalidLockfile(lockfile.to_owned()));
+        }
+        match lockfile.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => Ok(Some(dir)),
+            _ => Ok(Some(Path::new("."))),
+        }
+    }
+
+    /// the npm command for the audit call
+    ///
+    /// this checks for the node_modules directory in the directory npm will
+    /// be run in, see [AuditCommand::require_node_modules]
+    pub fn command(&self) -> Result<Command, crate::Error> {
+        let mut cmd = self.npm_command();
+        cmd.args(self.args()?);
+        let dir = self.current_dir()?;
+        let node_modules = dir.unwrap_or(Path::new(".")).join("node_modules");
+        if !node_modules.is_dir() {
+            if self.require_node_modules {
+                return Err(crate::Error::NodeModulesMissing(node_modules));
+            }
+            debug!(
+                "{} does not exist, auditing only the lockfile",
+                node_modules.display()
+            );
+            cmd.arg("--package-lock-only");
+        }
+        if let Some(dir) = dir {
+            cmd.current_dir(dir);
+        }
+        Ok(cmd)
+    }
+
+    /// the dependency groups omitted with [AuditCommand::omit] or
+    /// [AuditOptions::omit]
+    fn omitted(&self) -> Vec<DependencyGroup> {
+        let mut omit = self.omit.clone();
+        for group in &self.options.omit {
+            if !omit.contains(group) {
+                omit.push(*group);
+            }
+        }
+        omit
+    }
+
+    /// the arguments passed to npm for the audit call
+    ///
+    /// returns an error if a dependency group is both omitted and included
+    pub fn args(&self) -> Result<Vec<String>, crate::Error> {
+        let omit = self.omitted();
+        if let Some(group) = omit.iter().find(|g| self.include.contains(g)) {
+            return Err(crate::Error::ConflictingDependencyGroup(*group));
+        }
+
+        let mut args = vec!["audit".to_string()];
+        if self.dry_run_force {
+            args.extend(["fix", "--dry-run", "--force"].map(String::from));
+        }
+        args.push("--json".to_string());
+        for group in &omit {
+            args.push(format!("--omit={}", group));
+        }
+        for group in &self.include {
+            args.push(format!("--include={}", group));
+        }
+        if self.legacy_peer_deps {
+            args.push("--legacy-peer-deps".to_string());
+        }
+        if let Some(cache_dir) = &self.cache_dir {
+            args.push("--cache".to_string());
+            args.push(cache_dir.to_string_lossy().into_owned());
+        }
+        if self.offline {
+            args.push("--offline".to_string());
+        }
+        if let Some(level) = self.audit_level {
+            args.push(format!("--audit-level={}", level));
+        }
+        for (key, value) in &self.config {
+            if key.is_empty() || key.contains(|c: char| c.is_whitespace() || c == '=') {
+                return Err(crate::Error::InvalidConfigKey(key.to_string()));
+            }
+            args.push(format!("--{}={}", key, value));
+        }
+        Ok(args)
+    }
+
+    /// runs npm-audit with the configured settings
+    pub fn run(&self) -> Result<AuditResult, crate::Error> {
+        #[cfg(feature = "tarball")]
+        if let Some(tarball) = &self.tarball {
+            return self.run_tarball(tarball);
+        }
+
+        let mut cmd = self.command()?;
+
+        let npm_version = tolerate_probe_failure(self.probed_npm_version())?;
+
+        let generated_at = time::OffsetDateTime::now_utc();
+        let npm_start = Instant::now();
+        let output = crate::process::output(&mut cmd, self.stdin.as_deref(), self.options.timeout)?;
+        let npm_duration = npm_start.elapsed();
+
+        self.result_from_output(output, npm_version, generated_at, npm_duration)
+    }
+
+    /// like [AuditCommand::run] but without blocking the async executor
+    /// while npm runs
+    ///
+    /// the parsing still happens synchronously. If the returned future is
+    /// dropped before it finishes npm is killed. This requires the tokio
+    /// feature and has to be called within a tokio runtime.
+    #[cfg(feature = "tokio")]
+    pub async fn run_async(&self) -> Result<AuditResult, crate::Error> {
+        #[cfg(feature = "tarball")]
+        if let Some(tarball) = &self.tarball {
+            return self.run_tarball_async(tarball).await;
+        }
+        self.run_npm_async().await
+    }
+
+    /// the part of [AuditCommand::run_async] after a tarball was handled
+    #[cfg(feature = "tokio")]
+    async fn run_npm_async(&self) -> Result<AuditResult, crate::Error> {
+        let cmd = self.command()?;
+
+        let npm_version = tolerate_probe_failure(self.probed_npm_version_async().await)?;
+
+        let generated_at = time::OffsetDateTime::now_utc();
+        let npm_start = Instant::now();
+        let output =
+            crate::process::output_async(cmd, self.stdin.as_deref(), self.options.timeout).await?;
+        let npm_duration = npm_start.elapsed();
+
+        self.result_from_output(output, npm_version, generated_at, npm_duration)
+    }
+
+    /// interprets the output of the npm audit call
+    fn result_from_output(
+        &self,
+        output: std::process::Output,
+        npm_version: Result<Option<NpmVersion>, crate::Error>,
+        generated_at: time::OffsetDateTime,
+        npm_duration: Duration,
+    ) -> Result<AuditResult, crate::Error> {
+        if !output.status.success() {
+            warn!(
+                "npm audit did not return with a successful exit code: {}",
+                output.status
+            );
+            debug!(
+                "stdout:\n{}",
+                decode_output(&output.stdout, self.lossy_utf8)?
+            );
+            if !output.stderr.is_empty() {
+                warn!(
+                    "stderr:\n{}",
+                    decode_output(&output.stderr, self.lossy_utf8)?
+                );
+            }
+        }
+
+        if self.offline && !output.status.success() && is_offline_cache_miss(&output) {
+            return Err(crate::Error::NetworkRequired);
+        }
+
+        let update_requirement = if found_something(&output)? {
+            IndicatedUpdateRequirement::UpdateRequired
+        } else {
+            IndicatedUpdateRequirement::UpToDate
+        };
+
+        let parse_start = Instant::now();
+        let json_str = decode_output(&output.stdout, self.lossy_utf8)?;
+        let (npm_version, report_format) = match npm_version {
+            Ok(npm_version) => {
+                let report_format = self
+                    .report_format_for(npm_version.as_ref())
+                    .or_else(|| detect_report_format(&json_str))
+                    .unwrap_or(2);
+                (npm_version, report_format)
+            }
+            Err(e) => (None, detect_report_format(&json_str).ok_or(e)?),
+        };
+        debug!("Using report format {}", report_format);
+        let parsed = if self.dry_run_force {
+            parse_fix_preview(&json_str).map(|(data, fix_preview)| (data, Some(fix_preview)))
+        } else {
+            parse_audit_str(&json_str, report_format).map(|data| (data, None))
+        };
+        let (data, fix_preview) = match parsed {
+            // a failed npm call which printed no report at all, the reason is
+            // on stderr. Output containing a report which does not parse,
+            // e.g. because it is truncated, keeps its parse error.
+            Err(e)
+                if !output.status.success()
+                    && scan_json_object(&json_str) == JsonScan::NotFound =>
+            {
+                debug!("Could not parse the output of the failed npm call: {}", e);
+                return Err(crate::Error::NpmCommandFailed {
+                    status: output.status.code(),
+                    stderr: decode_output(&output.stderr, true)?.into_owned(),
+                });
+            }
+            parsed => parsed?,
+        };
+        let data = match (self.audit_level, data) {
+            (Some(level), NpmAuditData::Version2(data)) => {
+                NpmAuditData::Version2(data.filtered_by_severity(level))
+            }
+            (_, data) => data,
+        };
+        let parse_duration = parse_start.elapsed();
+
+        let update_requirement = if is_empty_json_object(&json_str) {
+            debug!("npm audit returned an empty object, treating it as a clean report");
+            IndicatedUpdateRequirement::UpToDate
+        } else {
+            update_requirement
+        };
+        let update_requirement = match self.actionable_floor {
+            Some(floor) if data.count_at_least(floor) > 0 => {
+                IndicatedUpdateRequirement::UpdateRequired
+            }
+            Some(floor) => {
+                debug!(
+                    "No vulnerabilities with severity {} or worse, no update required",
+                    floor.as_str()
+                );
+                IndicatedUpdateRequirement::UpToDate
+            }
+            None => update_requirement,
+        };
+
+        let timings = if self.timed {
+            debug!(
+                "npm audit took {:?}, parsing took {:?}",
+                npm_duration, parse_duration
+            );
+            Some(Timings {
+                npm: npm_duration,
+                parse: parse_duration,
+            })
+        } else {
+            None
+        };
+
+        Ok(AuditResult {
+            requirement: update_requirement,
+            exit_code: output.status.code(),
+            data,
+            timings,
+            fix_preview,
+            generated_at: Some(generated_at),
+            messages: parse_npm_messages(&decode_output(&output.stderr, true)?),
+            npm_version,
+            raw: self.keep_raw.then(|| json_str.into_owned()),
+        })
+    }
+}
+
+/// the report format produced by the npm version, None if the version could
+/// not be parsed
+fn version_report_format(version: &NpmVersion) -> Option<u8> {
+    if version.parsed().is_some() {
+        Some(version.report_format())
+    } else {
+        None
+    }
+}
+
+/// turns a failed npm version probe into a deferred error, the report format
+/// can still be detected from the output of npm audit and the error is only
+/// returned if that fails too
+fn tolerate_probe_failure<T>(
+    npm_version: Result<T, crate::Error>,
+) -> Result<Result<T, crate::Error>, crate::Error> {
+    match npm_version {
+        Err(e @ crate::Error::VersionProbeFailed { .. }) => {
+            warn!("{}, detecting the report format from the output", e);
+            Ok(Err(e))
+        }
+        npm_version => Ok(Ok(npm_version?)),
+    }
+}
+
+/// did npm fail because it was run with --offline and the cache did not
+/// contain a response it needed
+///
+/// npm reports this with the error code ENOTCACHED, on stderr and for --json
+/// also in the error object on stdout
+fn is_offline_cache_miss(output: &std::process::Output) -> bool {
+    /// the error code npm uses for a cache miss in offline mode
+    const SIGNATURE: &[u8] = b"ENOTCACHED";
+    [&output.stdout, &output.stderr]
+        .iter()
+        .any(|o| o.windows(SIGNATURE.len()).any(|w| w == SIGNATURE))
+}
+
+/// is the output of npm a bare `{}`
+///
+/// some configurations of npm print this instead of a report if there is
+/// nothing to report
+fn is_empty_json_object(json_str: &str) -> bool {
+    /// the whitespace characters allowed between JSON tokens
+    const JSON_WHITESPACE: [char; 4] = [' ', '\t', '\n', '\r'];
+    json_str
+        .trim_matches(JSON_WHITESPACE)
+        .strip_prefix('{')
+        .and_then(|s| s.strip_suffix('}'))
+        .is_some_and(|inner| inner.trim_matches(JSON_WHITESPACE).is_empty())
+}
+
+/// parses the JSON output of npm audit in the given report format (1 or 2)
+/// without running npm, e.g. stored reports or CI artifacts
+///
+/// an empty object is parsed as a clean version 2 report with all counts
+/// zero, regardless of the report format. Other report formats return
+/// [crate::Error::UnknownReportFormat].
+pub fn parse_audit_str(json_str: &str, report_format: u8) -> Result<NpmAuditData, crate::Error> {
+    if scan_json_object(json_str) == JsonScan::Truncated {
+        return Err(crate::Error::TruncatedOutput);
+    }
+    if is_empty_json_object(json_str) {
+        return Ok(NpmAuditData::Version2(NpmAuditDataV2::default()));
+    }
+    deserialize_report(
+        &mut serde_json::Deserializer::from_str(json_str),
+        report_format,
+    )
+}
+
+/// parses the JSON output of npm audit in the given report format (1 or 2)
+/// from a reader without loading it into memory first
+///
+/// the reader is not buffered here, pass e.g. a [std::io::BufReader] for
+/// files. Invalid UTF-8 is returned as a JSON error and input ending before
+/// the report is complete as [crate::Error::TruncatedOutput]. An empty
+/// object is parsed as a clean report like in [parse_audit_str].
+pub fn parse_audit_reader<R: std::io::Read>(
+    mut reader: R,
+    report_format: u8,
+) -> Result<NpmAuditData, crate::Error> {
+    let Some(start) = read_empty_json_object(&mut reader)? else {
+        return Ok(NpmAuditData::Version2(NpmAuditDataV2::default()));
+    };
+    let mut deserializer =
+        serde_json::Deserializer::from_reader(std::io::Read::chain(start.as_slice(), reader));
+    match deserialize_report(&mut deserializer, report_format) {
+        Err(crate::Error::SerdePathError(e)) if e.inner().is_eof() => {
+            Err(crate::Error::TruncatedOutput)
+        }
+        Err(e) => Err(e),
+        Ok(data) => {
+            deserializer.end()?;
+            Ok(data)
+        }
+    }
+}
+
+/// reads the start of the reader to check whether it contains only an empty
+/// object, like [is_empty_json_object] does for a string
+///
+/// returns None for an empty object, otherwise the bytes read so far which
+/// still have to be parsed together with the rest of the reader
+fn read_empty_json_object<R: std::io::Read>(
+    reader: &mut R,
+) -> Result<Option<Vec<u8>>, crate::Error> {
+    let mut start = Vec::new();
+    let mut opened = false;
+    loop {
+        let mut byte = [0];
+        match reader.read_exact(&mut byte) {
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(Some(start)),
+            result => result?,
+        }
+        start.push(byte[0]);
+        match byte[0] {
+            b' ' | b'\t' | b'\n' | b'\r' => {}
+            b'{' if !opened => opened = true,
+            b'}' if opened => break,
+            _ => return Ok(Some(start)),
+        }
+    }
+    serde_json::Deserializer::from_reader(reader).end()?;
+    Ok(None)
+}
+
+/// deserializes a report in the given report format
+fn deserialize_report<'de, D>(
+    deserializer: D,
+    report_format: u8,
+) -> Result<NpmAuditData, crate::Error>
+where
+    D: serde::Deserializer<'de, Error = serde_json::Error>,
+{
+    let data: NpmAuditData = match report_format {
+        1 => NpmAuditData::Version1(serde_path_to_error::deserialize(deserializer)?),
+        2 => NpmAuditData::Version2(serde_path_to_error::deserialize(deserializer)?),
+        _ => return Err(crate::Error::UnknownReportFormat(report_format)),
+    };
+    Ok(data)
+}
+
+/// parses output containing several audit reports back to back, e.g. when
+/// npm retried internally and printed two reports
+///
+/// all reports have to be in the given report format and complete, they
+/// are returned in the order they appear in
+pub fn parse_audit_multi(
+    json_str: &str,
+    report_format: u8,
+) -> Result<Vec<NpmAuditData>, crate::Error> {
+    let mut reports = Vec::new();
+    for value in serde_json::Deserializer::from_str(json_str).into_iter::<serde_json::Value>() {
+        let value = value?;
+        let report = match value.as_object() {
+            Some(object) if object.is_empty() => NpmAuditData::Version2(NpmAuditDataV2::default()),
+            _ => deserialize_report(value, report_format)?,
+        };
+        reports.push(report);
+    }
+    Ok(reports)
+}
+
+/// parses output containing several audit reports back to back and returns
+/// the last one, which is the one of the final attempt of npm
+///
+/// see [parse_audit_multi], output with only one report is parsed like
+/// [parse_audit_str] would
+pub fn parse_audit_last(json_str: &str, report_format: u8) -> Result<NpmAuditData, crate::Error> {
+    parse_audit_multi(json_str, report_format)?
+        .pop()
+        .ok_or(crate::Error::NoReport)
+}
+
+/// parses the JSON output of npm audit without running npm, the report
+/// format is determined from the content with [detect_report_format]
+///
+/// content which matches neither format is parsed as report version 2 so the
+/// error points at what is missing
+pub fn parse_audit_str_autodetect(json_str: &str) -> Result<NpmAuditData, crate::Error> {
+    parse_audit_str(json_str, detect_report_format(json_str).unwrap_or(2))
+}
+
+/// like [parse_audit_str_autodetect] but also returns the whole parsed JSON
+///
+/// the value is the source of truth for fields this crate does not model
+/// (yet), e.g. fields added by newer npm versions. The JSON is only parsed
+/// once, the typed data is deserialized from the value.
+pub fn parse_audit_with_value(
+    json_str: &str,
+) -> Result<(NpmAuditData, serde_json::Value), crate::Error> {
+    if scan_json_object(json_str) == JsonScan::Truncated {
+        return Err(crate::Error::TruncatedOutput);
+    }
+    let value: serde_json::Value =
+        serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_str(json_str))?;
+    let data = if value.as_object().is_some_and(|o| o.is_empty()) {
+        NpmAuditData::Version2(NpmAuditDataV2::default())
+    } else {
+        deserialize_report(&value, detect_report_format_value(&value).unwrap_or(2))?
+    };
+    Ok((data, value))
+}
+
+/// The package counts of an `npm audit fix --dry-run`
+///
+/// returned in [AuditResult::fix_preview] if [AuditCommand::dry_run_force]
+/// is enabled
+///
+/// all fields default to zero if npm does not include them, the shape of this
+/// output changes between npm versions
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct AuditFixPreview {
+    /// number of packages which would be added
+    pub added: u32,
+    /// number of packages which would be removed
+    pub removed: u32,
+    /// number of packages which would be changed
+    pub changed: u32,
+    /// number of packages which were audited
+    pub audited: u32,
+    /// number of packages looking for funding
+    pub funding: u32,
+    /// the whole output of npm as parsed JSON
+    ///
+    /// this gives access to fields which are not modelled here (yet). It is
+    /// not serialized, so a deserialized preview always has Null here.
+    #[serde(skip)]
+    pub raw: serde_json::Value,
+}
+
+/// the output of `npm audit fix --dry-run --json`
+#[derive(Debug, serde::Deserialize)]
+struct AuditFixOutput {
+    /// the package counts
+    #[serde(flatten)]
+    preview: AuditFixPreview,
+    /// the audit report
+    #[serde(default)]
+    audit: NpmAuditDataV2,
+}
+
+/// parses the output of `npm audit fix --dry-run --json`
+fn parse_fix_preview(json_str: &str) -> Result<(NpmAuditData, AuditFixPreview), crate::Error> {
+    if scan_json_object(json_str) == JsonScan::Truncated {
+        return Err(crate::Error::TruncatedOutput);
+    }
+    let raw: serde_json::Value = serde_json::from_str(json_str)?;
+    let mut output: AuditFixOutput = serde_path_to_error::deserialize(&raw)?;
+    output.preview.raw = raw;
+    Ok((NpmAuditData::Version2(output.audit), output.preview))
+}
+
+/// How long the parts of an npm call took
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timings {
+    /// time from spawning npm until it exited
+    ///
+    /// this does not include the npm --version call
+    pub npm: Duration,
+    /// time spent parsing the output of npm
+    pub parse: Duration,
+}
+
+/// The result of running an [AuditCommand]
+#[derive(Debug, Clone)]
+pub struct AuditResult {
+    /// what the exit code indicated about required updates
+    pub requirement: IndicatedUpdateRequirement,
+    /// the exit code of npm, None if it was terminated by a signal
+    ///
+    /// npm exits with 1 if it found vulnerabilities, other codes which
+    /// indicate an error are returned as [crate::Error::NpmCommandFailed]
+    /// instead of a result. This is the code npm returned even if
+    /// [AuditResult::requirement] was derived differently, e.g. with
+    /// [AuditCommand::actionable_floor].
+    pub exit_code: Option<i32>,
+    /// the parsed audit report
+    pub data: NpmAuditData,
+    /// how long the npm call and the parsing took
+    ///
+    /// only populated if [AuditCommand::timed] was enabled
+    pub timings: Option<Timings>,
+    /// how many packages a forced fix would change
+    ///
+    /// only populated if [AuditCommand::dry_run_force] was enabled
+    pub fix_preview: Option<AuditFixPreview>,
+    /// when npm was started for this report
+    ///
+    /// the reports themselves contain no timestamp, this is always set by
+    /// [AuditCommand::run], see [report_is_stale]
+    pub generated_at: Option<time::OffsetDateTime>,
+    /// the warnings and notices npm printed on stderr
+    pub messages: NpmMessages,
+    /// the npm version which produced the report
+    ///
+    /// this is the version given with [AuditCommand::npm_version] or probed
+    /// from npm, None if neither happened because the report format was
+    /// forced or the probe failed
+    pub npm_version: Option<NpmVersion>,
+    /// the unparsed output of npm
+    ///
+    /// only populated if [AuditCommand::keep_raw] was enabled
+    pub raw: Option<String>,
+}
+
+/// The warnings and notices npm prints on stderr, without the `npm WARN` or
+/// `npm notice` prefix
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NpmMessages {
+    /// `npm WARN` (`npm warn` since npm 10) lines, e.g. about deprecated
+    /// packages or config
+    pub warnings: Vec<String>,
+    /// `npm notice` lines, e.g. that a newer npm version is available
+    pub notices: Vec<String>,
+}
+
+/// collects the warnings and notices from the stderr output of npm
+///
+/// other lines, e.g. `npm ERR!`, and empty messages are skipped
+pub fn parse_npm_messages(stderr: &str) -> NpmMessages {
+    let mut messages = NpmMessages::default();
+    for line in stderr.lines() {
+        let Some(rest) = line.trim_end().strip_prefix("npm ") else {
+            continue;
+        };
+        let (list, message) = if let Some(message) = rest
+            .strip_prefix("WARN")
+            .or_else(|| rest.strip_prefix("warn"))
+        {
+            (&mut messages.warnings, message)
+        } else if let Some(message) = rest.strip_prefix("notice") {
+            (&mut messages.notices, message)
+        } else {
+            continue;
+        };
+        if !message.is_empty() && !message.starts_with(' ') {
+            continue;
+        }
+        let message = message.trim();
+        if !message.is_empty() {
+            list.push(message.to_string());
+        }
+    }
+    messages
+}
+
+/// has the lockfile been modified after the report was generated
+///
+/// compares the modification time of the lockfile with the given time, e.g.
+/// [AuditResult::generated_at]. A stale report might not reflect the
+/// current dependencies anymore.
+pub fn report_is_stale(
+    report_generated: time::OffsetDateTime,
+    lockfile: &Path,
+) -> std::io::Result<bool> {
+    let modified = time::OffsetDateTime::from(std::fs::metadata(lockfile)?.modified()?);
+    Ok(modified > report_generated)
+}
+
+/// main entry point for the npm-audit call
+pub fn audit() -> Result<(IndicatedUpdateRequirement, NpmAuditData), crate::Error> {
+    audit_with_options(&AuditOptions::default())
+}
+
+/// like [audit] but with the npm binary, working directory, timeout and
+/// omitted dependency groups from the options
+pub fn audit_with_options(
+    options: &AuditOptions,
+) -> Result<(IndicatedUpdateRequirement, NpmAuditData), crate::Error> {
+    let AuditResult {
+        requirement, data, ..
+    } = AuditCommand::new().options(options.clone()).run()?;
+    Ok((requirement, data))
+}
+
+/// like [audit] but without blocking the async executor while npm runs,
+/// see [AuditCommand::run_async]
+#[cfg(feature = "tokio")]
+pub async fn audit_async() -> Result<(IndicatedUpdateRequirement, NpmAuditData), crate::Error> {
+    let AuditResult {
+        requirement, data, ..
+    } = AuditCommand::new().run_async().await?;
+    Ok((requirement, data))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Error;
+    use tracing_test::traced_test;
+
+    /// this test requires a package.json and package-lock.json in the main crate
+    /// directory (working dir of the tests)
+    #[traced_test]
+    #[test]
+    fn test_run_npm_audit() -> Result<(), Error> {
+        audit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit_command_default_args() -> Result<(), Error> {
+        assert_eq!(AuditCommand::new().args()?, vec!["audit", "--json"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit_command_omit_include_args() -> Result<(), Error> {
+        let cmd = AuditCommand::new()
+            .omit(&[DependencyGroup::Dev, DependencyGroup::Optional])
+            .include(&[DependencyGroup::Peer]);
+        assert_eq!(
+            cmd.args()?,
+            vec![
+                "audit",
+                "--json",
+                "--omit=dev",
+                "--omit=optional",
+                "--include=peer"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit_command_omit_deduplicates() -> Result<(), Error> {
+        let cmd = AuditCommand::new()
+            .omit(&[DependencyGroup::Dev])
+            .omit(&[DependencyGroup::Dev]);
+        assert_eq!(cmd.args()?, vec!["audit", "--json", "--omit=dev"]);
+        let cmd = cmd.options(AuditOptions {
+            omit: vec![OmitKind::Optional, OmitKind::Dev],
+            ..AuditOptions::default()
+        });
+        assert_eq!(
+            cmd.args()?,
+            vec!["audit", "--json", "--omit=dev", "--omit=optional"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit_command_conflicting_group() {
+        let cmd = AuditCommand::new()
+            .omit(&[DependencyGroup::Dev])
+            .include(&[DependencyGroup::Dev]);
+        assert!(matches!(
+            cmd.args(),
+            Err(Error::ConflictingDependencyGroup(DependencyGroup::Dev))
+        ));
+    }
+
+    #[test]
+    fn test_audit_command_config_args() -> Result<(), Error> {
+        let cmd = AuditCommand::new()
+            .config("strict-ssl", "false")
+            .config("fetch-timeout", "60000");
+        assert_eq!(
+            cmd.args()?,
+            vec![
+                "audit",
+                "--json",
+                "--strict-ssl=false",
+                "--fetch-timeout=60000"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit_command_invalid_config_key() {
+        for key in ["", "strict ssl", "strict-ssl=false"] {
+            assert!(matches!(
+                AuditCommand::new().config(key, "false").args(),
+                Err(Error::InvalidConfigKey(k)) if k == key
+            ));
+        }
+    }
+
+    #[test]
+    fn test_audit_command_via_node() -> Result<(), Error> {
+        let cmd = AuditCommand::new()
+            .via_node(
+                PathBuf::from("/opt/node/bin/node"),
+                PathBuf::from("/opt/node/lib/node_modules/npm/bin/npm-cli.js"),
+            )
+            .command()?;
+        assert_eq!(cmd.get_program(), "/opt/node/bin/node");
+        let args: Vec<_> = cmd.get_args().take(3).collect();
+        assert_eq!(
+            args,
+            vec![
+                "/opt/node/lib/node_modules/npm/bin/npm-cli.js",
+                "audit",
+                "--json"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_is_idempotent() -> Result<(), Error> {
+        let mut data: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2.json"))?;
+        data.normalize();
+        let once = serde_json::to_string(&data)?;
+        data.normalize();
+        let twice = serde_json::to_string(&data)?;
+        assert_eq!(once, twice);
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_order() -> Result<(), Error> {
+        let mut data: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2.json"))?;
+        data.normalize();
+        let lodash = &data.vulnerabilities["lodash"];
+        assert_eq!(lodash.via.len(), 2);
+        assert_eq!(lodash.via[0].severity(), Some(&Severity::Critical));
+        assert_eq!(lodash.via[1].severity(), Some(&Severity::Moderate));
+        let glob_parent = &data.vulnerabilities["glob-parent"];
+        assert_eq!(glob_parent.effects, vec!["chokidar", "watchpack-chokidar2"]);
+        assert_eq!(
+            glob_parent.nodes,
+            vec![
+                "node_modules/glob-parent",
+                "node_modules/watchpack-chokidar2/node_modules/glob-parent"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_audit_report_version_string() -> Result<(), Error> {
+        let data: NpmAuditDataV2 = serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v2_report_version_string.json"
+        ))?;
+        assert_eq!(data.audit_report_version, Some(2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_audit_report_version_missing() -> Result<(), Error> {
+        let data: NpmAuditDataV2 = serde_json::from_str(
+            r#"{"vulnerabilities":{},"metadata":{"vulnerabilities":{"info":0,"low":0,"moderate":0,"high":0,"critical":0,"total":0},"dependencies":{"prod":1,"dev":0,"optional":0,"peer":0,"peerOptional":0,"total":1}}}"#,
+        )?;
+        assert_eq!(data.audit_report_version, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_report_format() {
+        assert_eq!(
+            detect_report_format(include_str!("../fixtures/npm_audit_v2.json")),
+            Some(2)
+        );
+        assert_eq!(
+            detect_report_format(include_str!(
+                "../fixtures/npm_audit_v2_no_report_version.json"
+            )),
+            Some(2)
+        );
+        assert_eq!(
+            detect_report_format(include_str!(
+                "../fixtures/npm_audit_v2_report_version_string.json"
+            )),
+            Some(2)
+        );
+        assert_eq!(
+            detect_report_format(include_str!("../fixtures/npm_audit_v1.json")),
+            Some(1)
+        );
+        assert_eq!(detect_report_format("{}"), None);
+        assert_eq!(detect_report_format("not json"), None);
+    }
+
+    #[test]
+    fn test_parse_v2_without_report_version() -> Result<(), Error> {
+        let json_str = include_str!("../fixtures/npm_audit_v2_no_report_version.json");
+        let data = parse_audit_str_autodetect(json_str)?;
+        let NpmAuditData::Version2(data) = data else {
+            panic!("expected a version 2 report");
+        };
+        assert_eq!(data.audit_report_version, None);
+        assert_eq!(data.metadata.vulnerabilities.total, 6);
+        Ok(())
+    }
+
+    #[test]
+    fn test_report_format_for_npm_version() {
+        assert_eq!(report_format_for_npm_version("6.14.18"), 1);
+        assert_eq!(
+            report_format_for_npm_version(AUDIT_REPORT_V2_MIN_NPM_VERSION),
+            2
+        );
+        assert_eq!(report_format_for_npm_version("8.19.4\n"), 2);
+        assert_eq!(report_format_for_npm_version(""), 2);
+    }
+
+    #[test]
+    fn test_breaking_fix_count() -> Result<(), Error> {
+        let data: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2_fixes.json"))?;
+        assert_eq!(data.breaking_fix_count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_upgrade_targets_highest_version() -> Result<(), Error> {
+        let mut data: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2_fixes.json"))?;
+        for (package, version) in [("axios", "5.1.0"), ("webpack-dev-server", "4.0.0")] {
+            data.vulnerabilities
+                .get_mut(package)
+                .expect("package in fixture")
+                .fix_available = Fix::Full {
+                name: "express".to_string(),
+                version: version.to_string(),
+                is_sem_ver_major: true,
+            };
+        }
+        assert_eq!(data.upgrade_targets().get("express"), Some(&"5.1.0"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_suggested_fix_command() -> Result<(), Error> {
+        let mut data: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2_fixes.json"))?;
+        assert_eq!(
+            data.suggested_fix_command(false),
+            vec!["npm", "audit", "fix"]
+        );
+        assert_eq!(
+            data.suggested_fix_command(true),
+            vec!["npm", "audit", "fix", "--force"]
+        );
+        // without the fix that does not name a target the targets can be
+        // installed explicitly
+        data.vulnerabilities.remove("semver");
+        assert_eq!(
+            data.suggested_fix_command(false),
+            vec!["npm", "audit", "fix"]
+        );
+        assert_eq!(
+            data.suggested_fix_command(true),
+            vec![
+                "npm",
+                "install",
+                "axios@0.28.1",
+                "express@5.0.0",
+                "webpack-dev-server@5.2.1"
+            ]
+        );
+        // only breaking fixes left
+        data.vulnerabilities.remove("axios");
+        assert!(data.suggested_fix_command(false).is_empty());
+        // no fixes left
+        data.vulnerabilities.remove("express");
+        data.vulnerabilities.remove("webpack-dev-server");
+        assert!(data.suggested_fix_command(false).is_empty());
+        assert!(data.suggested_fix_command(true).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_markdown_to_plaintext() {
+        let overview = "## Overview\n\nVersions of `lodash` before 4.17.19 are vulnerable to **Prototype Pollution**.\nSee the [GitHub issue](https://github.com/lodash/lodash/issues/4744) for _details_.\n\n## Remediation\n\n* Upgrade to version 4.17.19 or later\n";
+        assert_eq!(
+            markdown_to_plaintext(overview),
+            "Overview\n\nVersions of lodash before 4.17.19 are vulnerable to Prototype Pollution.\nSee the GitHub issue for details.\n\nRemediation\n\n- Upgrade to version 4.17.19 or later"
+        );
+    }
+
+    #[test]
+    fn test_markdown_to_plaintext_keeps_identifiers() {
+        assert_eq!(
+            markdown_to_plaintext("Setting `__proto__` via merge_with or set_with, 2 * 3"),
+            "Setting __proto__ via merge_with or set_with, 2 * 3"
+        );
+        assert_eq!(
+            markdown_to_plaintext("```js\nconst x = {};\n```\n> quoted *text*"),
+            "const x = {};\nquoted text"
+        );
+        assert_eq!(
+            markdown_to_plaintext("an unclosed [bracket and *star"),
+            "an unclosed [bracket and *star"
+        );
+        assert_eq!(
+            markdown_to_plaintext("#123 is not a heading"),
+            "#123 is not a heading"
+        );
+    }
+
+    #[test]
+    fn test_cve_parse() {
+        assert_eq!(
+            Cve::parse("cve-2021-23337").map(|c| c.to_string()),
+            Some("CVE-2021-23337".to_string())
+        );
+        assert_eq!(Cve::parse("2021-23337"), Cve::parse("CVE-2021-23337"));
+        assert_eq!(Cve::parse("GHSA-35jh-r3h4-6jhm"), None);
+        assert_eq!(Cve::parse("CVE-21-23337"), None);
+    }
+
+    #[test]
+    fn test_extract_cves() {
+        assert_eq!(
+            extract_cves("see https://nvd.nist.gov/vuln/detail/CVE-2020-7598 and cve-2021-23337."),
+            vec![
+                Cve::parse("CVE-2020-7598").unwrap(),
+                Cve::parse("CVE-2021-23337").unwrap()
+            ]
+        );
+        assert!(extract_cves("https://github.com/advisories/GHSA-vh95-rmgr-6w4m").is_empty());
+    }
+
+    #[test]
+    fn test_distinct_cve_count() -> Result<(), Error> {
+        let data = NpmAuditData::Version1(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v1_shared_cve.json"
+        ))?);
+        assert_eq!(data.distinct_cve_count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_vulnerable_fraction() -> Result<(), Error> {
+        let data = NpmAuditData::Version2(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v2_clean.json"
+        ))?);
+        assert_eq!(data.vulnerable_fraction(), Some(0.0));
+        let data = NpmAuditData::Version2(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v2.json"
+        ))?);
+        assert_eq!(data.vulnerable_fraction(), Some(6.0 / 471.0));
+        let mut data = NpmAuditData::Version1(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v1.json"
+        ))?);
+        assert_eq!(data.vulnerable_fraction(), Some(4.0 / 585.0));
+        if let NpmAuditData::Version1(data) = &mut data {
+            data.metadata.total_dependencies = 0;
+        }
+        assert_eq!(data.vulnerable_fraction(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_empty_object_as_clean_report() -> Result<(), Error> {
+        let json_str = include_str!("../fixtures/npm_audit_empty_object.json");
+        assert!(is_empty_json_object(json_str));
+        for report_format in [1, 2] {
+            let data = parse_audit_str(json_str, report_format)?;
+            let NpmAuditData::Version2(data) = &data else {
+                panic!("expected a version 2 report");
+            };
+            assert!(data.vulnerabilities.is_empty());
+            assert_eq!(data.metadata.vulnerabilities.total, 0);
+            assert_eq!(data.metadata.dependencies.total, 0);
+        }
+        assert!(!is_empty_json_object(include_str!(
+            "../fixtures/npm_audit_v2_clean.json"
+        )));
+        assert!(is_empty_json_object(" {\r\n\t} \n"));
+        assert!(!is_empty_json_object("{} {}"));
+        assert!(!is_empty_json_object("{"));
+        assert!(!is_empty_json_object("[]"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_path_roots() -> Result<(), Error> {
+        let data: NpmAuditDataV1 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v1.json"))?;
+        assert_eq!(
+            data.path_roots().into_iter().collect::<Vec<_>>(),
+            vec![
+                "chokidar",
+                "left-pad",
+                "lodash",
+                "mkdirp",
+                "optimist",
+                "webpack-bundle-analyzer"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_dev_only() -> Result<(), Error> {
+        let data: NpmAuditDataV1 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v1.json"))?;
+        assert!(!data.is_dev_only(1070355));
+        assert!(!data.is_dev_only(1067342));
+        assert!(data.is_dev_only(1068263));
+        assert!(!data.is_dev_only(1005365));
+        assert!(!data.is_dev_only(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_dev_only_packages() -> Result<(), Error> {
+        let data = NpmAuditData::Version2(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v2.json"
+        ))?);
+        let prod_report = NpmAuditData::Version2(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v2_omit_dev.json"
+        ))?);
+        assert_eq!(
+            data.dev_only_packages(&prod_report),
+            BTreeSet::from([
+                "chokidar".to_string(),
+                "glob-parent".to_string(),
+                "watchpack-chokidar2".to_string()
+            ])
+        );
+        assert!(prod_report.dev_only_packages(&prod_report).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_iter_vulnerabilities_dev_only_v1() -> Result<(), Error> {
+        let data = NpmAuditData::Version1(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v1.json"
+        ))?);
+        let dev_only: BTreeMap<String, bool> = data
+            .iter_vulnerabilities()
+            .map(|v| (v.package, v.dev_only))
+            .collect();
+        assert!(!dev_only["lodash"]);
+        assert!(!dev_only["minimist"]);
+        assert!(dev_only["glob-parent"]);
+        assert!(!dev_only["left-pad"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_iter_vulnerabilities_same_for_both_versions() -> Result<(), Error> {
+        let v1 = parse_audit_str(include_str!("../fixtures/npm_audit_v1.json"), 1)?;
+        let v2 = parse_audit_str(include_str!("../fixtures/npm_audit_v2.json"), 2)?;
+        for data in [v1, v2] {
+            let minimist = data
+                .iter_vulnerabilities()
+                .find(|v| v.package == "minimist")
+                .expect("minimist is vulnerable in both fixtures");
+            assert_eq!(minimist.range, "<0.2.1");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_iter_vulnerabilities_dev_only_v2() -> Result<(), Error> {
+        let data = NpmAuditData::Version2(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v2.json"
+        ))?);
+        assert_eq!(data.iter_vulnerabilities().count(), 4);
+        assert!(data.iter_vulnerabilities().all(|v| !v.dev_only));
+        let prod_report = NpmAuditData::Version2(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v2_omit_dev.json"
+        ))?);
+        let vulnerabilities: Vec<UnifiedVulnerability> = data
+            .iter_vulnerabilities_with_prod_report(&prod_report)
+            .collect();
+        assert_eq!(vulnerabilities.len(), 4);
+        for vulnerability in vulnerabilities {
+            assert_eq!(
+                vulnerability.dev_only,
+                vulnerability.package == "glob-parent",
+                "{}",
+                vulnerability.package
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_cwe() {
+        assert_eq!(parse_cwe("CWE-79"), Some(79));
+        assert_eq!(parse_cwe("cwe-1321"), Some(1321));
+        assert_eq!(parse_cwe("400"), Some(400));
+        assert_eq!(parse_cwe("CWE-"), None);
+        assert_eq!(parse_cwe("NVD-CWE-Other"), None);
+    }
+
+    #[test]
+    fn test_advisories_by_cwe() -> Result<(), Error> {
+        let data: NpmAuditDataV1 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v1_cwes.json"))?;
+        let by_cwe = data.advisories_by_cwe();
+        let ids: BTreeMap<u32, Vec<u64>> = by_cwe
+            .iter()
+            .map(|(cwe, advisories)| (*cwe, advisories.iter().map(|a| a.id).collect()))
+            .collect();
+        assert_eq!(
+            ids,
+            BTreeMap::from([
+                (77, vec![1070355]),
+                (79, vec![1001001]),
+                (94, vec![1070355]),
+                (400, vec![1001002, 1001003]),
+                (1321, vec![1001003]),
+            ])
+        );
+        assert!(!by_cwe.values().flatten().any(|a| a.id == 1001004));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fingerprint_is_order_independent() -> Result<(), Error> {
+        let json_str = include_str!("../fixtures/npm_audit_v2.json");
+        let data = NpmAuditData::Version2(serde_json::from_str(json_str)?);
+        let mut reordered: NpmAuditDataV2 = serde_json::from_str(json_str)?;
+        for package in reordered.vulnerabilities.values_mut() {
+            package.via.reverse();
+            package.effects.reverse();
+        }
+        let reordered = NpmAuditData::Version2(reordered);
+        assert_eq!(data.fingerprint(), reordered.fingerprint());
+        let clean = NpmAuditData::Version2(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v2_clean.json"
+        ))?);
+        assert_ne!(data.fingerprint(), clean.fingerprint());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_url() -> Result<(), Error> {
+        let json_str = include_str!("../fixtures/npm_audit_v2_cve_urls.json");
+        let data: NpmAuditDataV2 = serde_json::from_str(json_str)?;
+        let mut moved = data.clone();
+        for package in moved.vulnerabilities.values_mut() {
+            for via in &mut package.via {
+                if let Vulnerability::Full { url, source, .. } = via {
+                    if !url.contains("CVE-") {
+                        *url = format!("https://www.npmjs.com/advisories/{}", source);
+                    }
+                }
+            }
+        }
+        assert_ne!(moved, data);
+        let data = NpmAuditData::Version2(data);
+        assert_eq!(
+            data.fingerprint(),
+            NpmAuditData::Version2(moved).fingerprint()
+        );
+        let v1: NpmAuditDataV1 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v1.json"))?;
+        let mut moved = v1.clone();
+        for advisory in moved.advisories.values_mut() {
+            advisory.url = format!("https://github.com/advisories/{}", advisory.id);
+        }
+        assert_eq!(
+            NpmAuditData::Version1(v1.clone()).fingerprint(),
+            NpmAuditData::Version1(moved).fingerprint()
+        );
+        let mut other = v1.clone();
+        if let Some(advisory) = other.advisories.values_mut().next() {
+            advisory.cves = Some(vec!["CVE-2000-0001".to_string()]);
+        }
+        assert_ne!(
+            NpmAuditData::Version1(v1).fingerprint(),
+            NpmAuditData::Version1(other).fingerprint()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_fingerprint_severity_and_package() -> Result<(), Error> {
+        let v1: NpmAuditDataV1 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v1.json"))?;
+        let fingerprint = NpmAuditData::Version1(v1.clone()).fingerprint();
+        let mut downgraded = v1.clone();
+        if let Some(advisory) = downgraded.advisories.get_mut("1070355") {
+            advisory.severity = Severity::Low;
+        }
+        assert_ne!(
+            fingerprint,
+            NpmAuditData::Version1(downgraded).fingerprint()
+        );
+        let mut renamed = v1;
+        if let Some(advisory) = renamed.advisories.get_mut("1070355") {
+            advisory.module_name = Some("lodash-es".to_string());
+        }
+        assert_ne!(fingerprint, NpmAuditData::Version1(renamed).fingerprint());
+        let data: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2.json"))?;
+        let mut downgraded = data.clone();
+        for package in downgraded.vulnerabilities.values_mut() {
+            for via in &mut package.via {
+                if let Vulnerability::Full { severity, .. } = via {
+                    *severity = Severity::Info;
+                }
+            }
+        }
+        assert_ne!(
+            NpmAuditData::Version2(data).fingerprint(),
+            NpmAuditData::Version2(downgraded).fingerprint()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_via_source_id() -> Result<(), Error> {
+        let data: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2_via_source_id.json"))?;
+        let via = &data.vulnerabilities["minimist"].via;
+        assert_eq!(via.len(), 1);
+        assert!(matches!(via[0], Vulnerability::SourceId(1066786)));
+        assert!(matches!(
+            data.vulnerabilities["optimist"].via[0],
+            Vulnerability::NameOnly(ref name) if name == "minimist"
+        ));
+        assert_eq!(via[0].severity(), None);
+        assert_eq!(via[0].name(), "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_remediation_plan() -> Result<(), Error> {
+        let data: NpmAuditDataV1 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v1.json"))?;
+        assert_eq!(
+            data.remediation_plan(),
+            vec![
+                PlannedChange {
+                    module: "lodash".to_string(),
+                    target: "4.17.21".to_string(),
+                    is_major: false,
+                    advisory_ids: vec![1070355],
+                },
+                PlannedChange {
+                    module: "webpack-bundle-analyzer".to_string(),
+                    target: "4.9.0".to_string(),
+                    is_major: true,
+                    advisory_ids: vec![1070355],
+                },
+                PlannedChange {
+                    module: "minimist".to_string(),
+                    target: "0.2.4".to_string(),
+                    is_major: false,
+                    advisory_ids: vec![1067342],
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_audit_str() -> Result<(), Error> {
+        let v1 = include_str!("../fixtures/npm_audit_v1.json");
+        let v2 = include_str!("../fixtures/npm_audit_v2.json");
+        assert!(matches!(parse_audit_str(v1, 1)?, NpmAuditData::Version1(_)));
+        assert!(matches!(parse_audit_str(v2, 2)?, NpmAuditData::Version2(_)));
+        assert!(parse_audit_str(v1, 2).is_err());
+        assert!(matches!(
+            parse_audit_str(v2, 3),
+            Err(Error::UnknownReportFormat(3))
+        ));
+        assert!(matches!(
+            parse_audit_str_autodetect(v1)?,
+            NpmAuditData::Version1(_)
+        ));
+        assert!(matches!(
+            parse_audit_str_autodetect(v2)?,
+            NpmAuditData::Version2(_)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_report_is_stale() -> Result<(), Error> {
+        let dir = crate::test_dir("report-is-stale");
+        let lockfile = dir.join("package-lock.json");
+        let before = time::OffsetDateTime::now_utc() - time::Duration::hours(1);
+        std::fs::write(&lockfile, "{}")?;
+        let after = time::OffsetDateTime::now_utc() + time::Duration::hours(1);
+        assert!(report_is_stale(before, &lockfile)?);
+        assert!(!report_is_stale(after, &lockfile)?);
+        assert!(report_is_stale(before, &dir.join("missing.json")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_audit_multi() -> Result<(), Error> {
+        let json_str = include_str!("../fixtures/npm_audit_v2_concatenated.json");
+        let reports = parse_audit_multi(json_str, 2)?;
+        assert_eq!(reports.len(), 2);
+        assert!(reports[0].package_severities().is_empty());
+        assert_eq!(reports[1].package_severities().len(), 6);
+        let last = parse_audit_last(json_str, 2)?;
+        assert_eq!(last.package_severities().len(), 6);
+        assert!(matches!(parse_audit_last("", 2), Err(Error::NoReport)));
+        Ok(())
+    }
+
+    /// answers `npm --version` in the fake npm scripts of the audit tests
+    #[cfg(unix)]
+    const FAKE_NPM_VERSION: &str = r#"if [ "$1" = "--version" ]; then echo 10.8.2; exit 0; fi"#;
+
+    /// options for a fake npm in the test directory `name` which answers
+    /// `--version` with 10.8.2 and runs `script` otherwise, `files` are
+    /// written to the test directory first
+    #[cfg(unix)]
+    fn fake_npm_options(
+        name: &str,
+        script: &str,
+        files: &[(&str, &str)],
+    ) -> Result<AuditOptions, Error> {
+        let dir = crate::test_dir(name);
+        for (file, content) in files {
+            std::fs::write(dir.join(file), content)?;
+        }
+        let npm = crate::fake_npm(&dir, &format!("{}\n{}", FAKE_NPM_VERSION, script));
+        Ok(AuditOptions {
+            npm_path: Some(npm),
+            working_dir: Some(dir),
+            timeout: None,
+            omit: Vec::new(),
+        })
+    }
+
+    /// runs an audit with the fake npm from [fake_npm_options]
+    #[cfg(unix)]
+    fn run_fake_audit(
+        name: &str,
+        script: &str,
+        files: &[(&str, &str)],
+    ) -> Result<AuditResult, Error> {
+        AuditCommand::new()
+            .options(fake_npm_options(name, script, files)?)
+            .run()
+    }
+
+    /// a fake npm script printing the report.json written to the test directory
+    #[cfg(unix)]
+    const PRINT_REPORT: &str = "cat report.json; exit 1";
+
+    #[cfg(unix)]
+    #[test]
+    fn test_audit_with_options() -> Result<(), Error> {
+        let mut options = fake_npm_options("audit-with-options", PRINT_REPORT, &[])?;
+        let project = options
+            .working_dir
+            .take()
+            .expect("fake npm options have a working directory")
+            .join("project");
+        std::fs::create_dir(&project)?;
+        std::fs::write(
+            project.join("report.json"),
+            include_str!("../fixtures/npm_audit_v2.json"),
+        )?;
+        options.working_dir = Some(project);
+        let (requirement, data) = audit_with_options(&options)?;
+        assert_eq!(requirement, IndicatedUpdateRequirement::UpdateRequired);
+        assert_eq!(data.package_severities().len(), 6);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_audit_stdin() -> Result<(), Error> {
+        let result = AuditCommand::new()
+            .options(fake_npm_options("audit-stdin", "cat; exit 1", &[])?)
+            .stdin(include_bytes!("../fixtures/npm_audit_v2.json").to_vec())
+            .run()?;
+        assert_eq!(result.data.package_severities().len(), 6);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_audit_failed_without_report() -> Result<(), Error> {
+        match run_fake_audit(
+            "audit-failed-without-report",
+            r#"echo "npm ERR! code ENOLOCK" >&2; exit 1"#,
+            &[],
+        ) {
+            Err(Error::NpmCommandFailed { status, stderr }) => {
+                assert_eq!(status, Some(1));
+                assert_eq!(stderr, "npm ERR! code ENOLOCK\n");
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_audit_failed_with_truncated_report() -> Result<(), Error> {
+        let report = include_str!("../fixtures/npm_audit_v2.json");
+        let result = run_fake_audit(
+            "audit-failed-with-truncated-report",
+            r#"echo "npm ERR! code ECONNRESET" >&2; cat report.json; exit 1"#,
+            &[("report.json", &report[..report.len() / 2])],
+        );
+        assert!(
+            matches!(result, Err(Error::TruncatedOutput)),
+            "{:?}",
+            result
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_audit_version_probe_failed() -> Result<(), Error> {
+        let dir = crate::test_dir("audit-version-probe-failed");
+        std::fs::write(
+            dir.join("report.json"),
+            include_str!("../fixtures/npm_audit_v2.json"),
+        )?;
+        let npm = crate::fake_npm(
+            &dir,
+            r#"if [ "$1" = "--version" ]; then echo "npm: broken install" >&2; exit 1; fi
+if [ -f report.json ]; then cat report.json; else echo "garbage"; fi; exit 1"#,
+        );
+        let options = AuditOptions {
+            npm_path: Some(npm),
+            working_dir: Some(dir.clone()),
+            timeout: None,
+            omit: Vec::new(),
+        };
+        let result = AuditCommand::new().options(options.clone()).run()?;
+        assert!(matches!(result.data, NpmAuditData::Version2(_)));
+        std::fs::remove_file(dir.join("report.json"))?;
+        match AuditCommand::new().options(options).run() {
+            Err(Error::VersionProbeFailed { stderr }) => {
+                assert_eq!(stderr, "npm: broken install\n");
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[cfg(all(unix, feature = "tokio"))]
+    #[tokio::test]
+    async fn test_audit_run_async() -> Result<(), Error> {
+        let options = fake_npm_options(
+            "audit-run-async",
+            PRINT_REPORT,
+            &[("report.json", include_str!("../fixtures/npm_audit_v2.json"))],
+        )?;
+        let command = AuditCommand::new().options(options);
+        let result = command.run_async().await?;
+        let expected = command.run()?;
+        assert_eq!(result.requirement, expected.requirement);
+        assert_eq!(result.data, expected.data);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_audit_omit_dev() -> Result<(), Error> {
+        let options = fake_npm_options(
+            "audit-omit-dev",
+            r#"for arg in "$@"; do
+  if [ "$arg" = "--omit=dev" ]; then cat prod.json; exit 1; fi
+done
+cat full.json; exit 1"#,
+            &[
+                ("full.json", include_str!("../fixtures/npm_audit_v2.json")),
+                (
+                    "prod.json",
+                    include_str!("../fixtures/npm_audit_v2_omit_dev.json"),
+                ),
+            ],
+        )?;
+        let all = AuditCommand::new().options(options.clone()).run()?;
+        assert!(all.data.package_severities().contains_key("glob-parent"));
+        let prod = AuditCommand::new()
+            .options(options.clone())
+            .omit(&[OmitKind::Dev])
+            .run()?;
+        assert!(!prod.data.package_severities().contains_key("glob-parent"));
+        assert!(prod.data.package_severities().contains_key("lodash"));
+        let (_, prod) = audit_with_options(&AuditOptions {
+            omit: vec![OmitKind::Dev],
+            ..options
+        })?;
+        assert!(!prod.package_severities().contains_key("glob-parent"));
+        assert!(prod.package_severities().contains_key("lodash"));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_audit_timeout() -> Result<(), Error> {
+        let result = AuditCommand::new()
+            .options(AuditOptions {
+                timeout: Some(Duration::from_millis(200)),
+                ..fake_npm_options("audit-timeout", "sleep 30", &[])?
+            })
+            .run();
+        assert!(matches!(result, Err(Error::Timeout(_))));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_audit_version_probe_timeout() -> Result<(), Error> {
+        let dir = crate::test_dir("audit-version-probe-timeout");
+        let npm = crate::fake_npm(
+            &dir,
+            r#"if [ "$1" = "--version" ]; then sleep 30; fi; echo "{}""#,
+        );
+        let start = Instant::now();
+        let result = AuditCommand::new()
+            .options(AuditOptions {
+                npm_path: Some(npm),
+                working_dir: Some(dir),
+                timeout: Some(Duration::from_millis(200)),
+                omit: Vec::new(),
+            })
+            .run();
+        assert!(matches!(result, Err(Error::Timeout(_))));
+        assert!(start.elapsed() < Duration::from_secs(10));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_actionable_floor() -> Result<(), Error> {
+        let run = |name: &str, report: &str, floor: Option<Severity>| {
+            let mut command = AuditCommand::new().options(fake_npm_options(
+                name,
+                PRINT_REPORT,
+                &[("report.json", report)],
+            )?);
+            if let Some(floor) = floor {
+                command = command.actionable_floor(floor);
+            }
+            command.run().map(|result| result.requirement)
+        };
+        let low_only = include_str!("../fixtures/npm_audit_v2_low_only.json");
+        assert_eq!(
+            run("audit-floor-low-default", low_only, None)?,
+            IndicatedUpdateRequirement::UpdateRequired
+        );
+        assert_eq!(
+            run(
+                "audit-floor-low-moderate",
+                low_only,
+                Some(Severity::Moderate)
+            )?,
+            IndicatedUpdateRequirement::UpToDate
+        );
+        assert_eq!(
+            run("audit-floor-low-low", low_only, Some(Severity::Low))?,
+            IndicatedUpdateRequirement::UpdateRequired
+        );
+        assert_eq!(
+            run(
+                "audit-floor-moderate",
+                include_str!("../fixtures/npm_audit_v2_cvss.json"),
+                Some(Severity::Moderate)
+            )?,
+            IndicatedUpdateRequirement::UpdateRequired
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_audit_level() -> Result<(), Error> {
+        let level: Severity = "high".parse()?;
+        let command = AuditCommand::new().audit_level(level);
+        assert_eq!(
+            command.args()?,
+            vec!["audit", "--json", "--audit-level=high"]
+        );
+        let result = command
+            .options(fake_npm_options(
+                "audit-level",
+                r#"for arg in "$@"; do
+  if [ "$arg" = "--audit-level=high" ]; then cat report.json; exit 1; fi
+done
+exit 2"#,
+                &[(
+                    "report.json",
+                    include_str!("../fixtures/npm_audit_v2_cvss.json"),
+                )],
+            )?)
+            .run()?;
+        assert_eq!(
+            result
+                .data
+                .package_severities()
+                .into_keys()
+                .collect::<Vec<_>>(),
+            vec!["chokidar", "glob-parent", "lodash", "watchpack-chokidar2"]
+        );
+        assert_eq!(result.data.severity_counts()[&Severity::Moderate], 0);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_audit_exit_code() -> Result<(), Error> {
+        let run = |name: &str, report: &str, exit_code: i32| {
+            run_fake_audit(
+                name,
+                &format!(
+                    r#"echo "npm ERR! exit code" >&2; cat report.json; exit {}"#,
+                    exit_code
+                ),
+                &[("report.json", report)],
+            )
+        };
+        let clean = run(
+            "audit-exit-code-clean",
+            include_str!("../fixtures/npm_audit_v2_clean.json"),
+            0,
+        )?;
+        assert_eq!(clean.exit_code, Some(0));
+        assert_eq!(clean.requirement, IndicatedUpdateRequirement::UpToDate);
+        let found = run(
+            "audit-exit-code-found",
+            include_str!("../fixtures/npm_audit_v2.json"),
+            1,
+        )?;
+        assert_eq!(found.exit_code, Some(1));
+        assert_eq!(
+            found.requirement,
+            IndicatedUpdateRequirement::UpdateRequired
+        );
+        for exit_code in [2, 3] {
+            match run(
+                &format!("audit-exit-code-error-{}", exit_code),
+                include_str!("../fixtures/npm_audit_v2.json"),
+                exit_code,
+            ) {
+                Err(Error::NpmCommandFailed { status, stderr }) => {
+                    assert_eq!(status, Some(exit_code));
+                    assert_eq!(stderr, "npm ERR! exit code\n");
+                }
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_severity_display_and_from_str() -> Result<(), Error> {
+        for severity in [
+            Severity::None,
+            Severity::Info,
+            Severity::Low,
+            Severity::Moderate,
+            Severity::High,
+            Severity::Critical,
+        ] {
+            assert_eq!(severity.to_string(), severity.as_str());
+            assert_eq!(severity.to_string().parse::<Severity>()?, severity);
+        }
+        assert_eq!(Severity::Moderate.to_string(), "moderate");
+        // the values of npm's --audit-level
+        for level in ["none", "info", "low", "moderate", "high", "critical"] {
+            assert_eq!(level.parse::<Severity>()?.as_str(), level);
+        }
+        assert!(matches!(
+            "severe".parse::<Severity>(),
+            Err(Error::UnknownSeverity(s)) if s == "severe"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_npm_messages() {
+        let messages = parse_npm_messages(include_str!("../fixtures/npm_stderr_notices.txt"));
+        assert_eq!(
+            messages.warnings,
+            vec![
+                "config production Use `--omit=dev` instead.",
+                "deprecated inflight@1.0.6: This module is not supported, and leaks memory.",
+            ]
+        );
+        assert_eq!(
+            messages.notices,
+            vec![
+                "New major version of npm available! 9.8.1 -> 10.8.2",
+                "Changelog: https://github.com/npm/cli/releases/tag/v10.8.2",
+                "Run npm install -g npm@10.8.2 to update!",
+            ]
+        );
+        assert_eq!(
+            parse_npm_messages("npm warning-ish\n"),
+            NpmMessages::default()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_audit_messages() -> Result<(), Error> {
+        let result = run_fake_audit(
+            "audit-messages",
+            "cat stderr.txt >&2; cat report.json; exit 1",
+            &[
+                ("report.json", include_str!("../fixtures/npm_audit_v2.json")),
+                (
+                    "stderr.txt",
+                    include_str!("../fixtures/npm_stderr_notices.txt"),
+                ),
+            ],
+        )?;
+        assert_eq!(result.messages.warnings.len(), 2);
+        assert_eq!(result.messages.notices.len(), 3);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_audit_result_metadata() -> Result<(), Error> {
+        let report = include_str!("../fixtures/npm_audit_v2.json");
+        let command = AuditCommand::new().options(fake_npm_options(
+            "audit-result-metadata",
+            PRINT_REPORT,
+            &[("report.json", report)],
+        )?);
+        let result = command.clone().keep_raw(true).timed(true).run()?;
+        assert_eq!(
+            result.npm_version.as_ref().map(NpmVersion::raw),
+            Some("10.8.2")
+        );
+        assert_eq!(result.raw.as_deref(), Some(report));
+        assert!(result.timings.is_some());
+        assert_eq!(result.exit_code, Some(1));
+        let result = command.force_report_format(2).run()?;
+        assert_eq!(result.npm_version, None);
+        assert_eq!(result.raw, None);
+        assert_eq!(result.timings, None);
+        Ok(())
+    }
+
+    /// an audit command with a fake npm which reports npm 6 as its version
+    /// only when run in the test directory
+    #[cfg(unix)]
+    fn probe_in_working_dir_command(name: &str) -> Result<AuditCommand, Error> {
+        let dir = crate::test_dir(name);
+        std::fs::write(
+            dir.join("report.json"),
+            include_str!("../fixtures/npm_audit_v1.json"),
+        )?;
+        let npm = crate::fake_npm(
+            &dir,
+            r#"if [ "$1" = "--version" ]; then
+  if [ -f report.json ]; then echo 6.14.18; else echo 10.8.2; fi; exit 0
+fi
+cat report.json; exit 1"#,
+        );
+        Ok(AuditCommand::new().options(AuditOptions {
+            npm_path: Some(npm),
+            working_dir: Some(dir),
+            timeout: None,
+            omit: Vec::new(),
+        }))
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_probe_in_working_dir() -> Result<(), Error> {
+        let result = probe_in_working_dir_command("audit-probe-in-working-dir")?.run()?;
+        assert_eq!(
+            result.npm_version.as_ref().map(NpmVersion::raw),
+            Some("6.14.18")
+        );
+        assert!(matches!(result.data, NpmAuditData::Version1(_)));
+        Ok(())
+    }
+
+    #[cfg(all(unix, feature = "tokio"))]
+    #[tokio::test]
+    async fn test_probe_in_working_dir_async() -> Result<(), Error> {
+        let result = probe_in_working_dir_command("audit-probe-in-working-dir-async")?
+            .run_async()
+            .await?;
+        assert_eq!(
+            result.npm_version.as_ref().map(NpmVersion::raw),
+            Some("6.14.18")
+        );
+        assert!(matches!(result.data, NpmAuditData::Version1(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_construct_audit_result() -> Result<(), Error> {
+        let data = parse_audit_str(include_str!("../fixtures/npm_audit_v2_clean.json"), 2)?;
+        let result = AuditResult {
+            requirement: IndicatedUpdateRequirement::UpToDate,
+            exit_code: Some(0),
+            data: data.clone(),
+            timings: None,
+            fix_preview: None,
+            generated_at: None,
+            messages: NpmMessages::default(),
+            npm_version: Some(NpmVersion::from_raw("10.8.2")),
+            raw: None,
+        };
+        let AuditResult {
+            requirement,
+            data: result_data,
+            npm_version,
+            ..
+        } = result.clone();
+        assert_eq!(requirement, IndicatedUpdateRequirement::UpToDate);
+        assert_eq!(result_data, data);
+        assert_eq!(npm_version.map(|v| v.report_format()), Some(2));
+        assert!(result.messages.warnings.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_severity_percentages() -> Result<(), Error> {
+        let data = parse_audit_str(include_str!("../fixtures/npm_audit_v1.json"), 1)?;
+        let percentages = data.severity_percentages();
+        assert_eq!(percentages[&Severity::Info], 0.0);
+        assert_eq!(percentages[&Severity::Low], 25.0);
+        assert_eq!(percentages[&Severity::Moderate], 25.0);
+        assert_eq!(percentages[&Severity::High], 50.0);
+        assert_eq!(percentages[&Severity::Critical], 0.0);
+        let data = parse_audit_str(include_str!("../fixtures/npm_audit_v2.json"), 2)?;
+        let sum: f64 = data.severity_percentages().values().sum();
+        assert!((sum - 100.0).abs() < 1e-9);
+        let data = parse_audit_str(include_str!("../fixtures/npm_audit_v2_clean.json"), 2)?;
+        assert!(data.severity_percentages().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_severity_and_count_at_least() -> Result<(), Error> {
+        let v1 = parse_audit_str(include_str!("../fixtures/npm_audit_v1.json"), 1)?;
+        assert_eq!(v1.max_severity(), Severity::High);
+        assert_eq!(v1.count_at_least(Severity::High), 2);
+        assert_eq!(v1.count_at_least(Severity::Critical), 0);
+        let v2 = parse_audit_str(include_str!("../fixtures/npm_audit_v2.json"), 2)?;
+        assert_eq!(v2.max_severity(), Severity::Critical);
+        assert_eq!(v2.count_at_least(Severity::High), 4);
+        assert_eq!(v2.count_at_least(Severity::None), 6);
+        let clean = parse_audit_str(include_str!("../fixtures/npm_audit_v2_clean.json"), 2)?;
+        assert_eq!(clean.max_severity(), Severity::None);
+        assert_eq!(clean.count_at_least(Severity::Info), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_v2_without_total() -> Result<(), Error> {
+        let json = include_str!("../fixtures/npm_audit_v2_npm7_no_total.json");
+        assert_eq!(detect_report_format(json), Some(2));
+        let NpmAuditData::Version2(data) = parse_audit_str(json, 2)? else {
+            panic!("expected a version 2 report");
+        };
+        assert_eq!(data.metadata.vulnerabilities.total, 6);
+        let counts: VulnerabilityCountsV2 = serde_json::from_str(
+            r#"{"info": 0, "low": 1, "moderate": 0, "high": 4294967295, "critical": 1}"#,
+        )?;
+        assert_eq!(counts.total, u32::MAX);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_fix_targets() -> Result<(), Error> {
+        let data: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2.json"))?;
+        assert!(data.invalid_fix_targets().is_empty());
+        let data: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2_bad_fix_target.json"))?;
+        assert_eq!(
+            data.invalid_fix_targets(),
+            vec![("lodash", "latest"), ("mkdirp", "0.5")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_clone_for_threads() -> Result<(), Error> {
+        let data = parse_audit_str(include_str!("../fixtures/npm_audit_v2.json"), 2)?;
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let copy = data.clone();
+                std::thread::spawn(move || copy.package_severities())
+            })
+            .collect();
+        for handle in handles {
+            let severities = handle.join().expect("thread panicked");
+            assert_eq!(severities, data.package_severities());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_reports_compare_equal_after_roundtrip() -> Result<(), Error> {
+        for (json, format) in [
+            (include_str!("../fixtures/npm_audit_v1.json"), 1),
+            (include_str!("../fixtures/npm_audit_v2.json"), 2),
+        ] {
+            let data = parse_audit_str(json, format)?;
+            let reparsed = parse_audit_str(&serde_json::to_string(&data)?, format)?;
+            assert_eq!(data, reparsed);
+        }
+        let v2 = parse_audit_str(include_str!("../fixtures/npm_audit_v2.json"), 2)?;
+        assert_ne!(v2, v2.redacted());
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_all() -> Result<(), Error> {
+        let full: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2.json"))?;
+        let mut other: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2_omit_dev.json"))?;
+        let lodash = other
+            .vulnerabilities
+            .get_mut("lodash")
+            .expect("fixture contains lodash");
+        lodash.severity = Severity::Low;
+        lodash
+            .nodes
+            .push("packages/a/node_modules/lodash".to_string());
+        let merged = merge_all([full.clone(), other.clone(), NpmAuditDataV2::default()]);
+        assert_eq!(merged.vulnerabilities.len(), full.vulnerabilities.len());
+        let lodash = &merged.vulnerabilities["lodash"];
+        assert_eq!(lodash.severity, full.vulnerabilities["lodash"].severity);
+        assert_eq!(
+            lodash.nodes,
+            vec!["node_modules/lodash", "packages/a/node_modules/lodash"]
+        );
+        assert_eq!(
+            merged.metadata.vulnerabilities.total,
+            full.metadata.vulnerabilities.total
+        );
+        assert_eq!(
+            merged.metadata.dependencies.total,
+            full.metadata.dependencies.total + other.metadata.dependencies.total
+        );
+        assert_eq!(merge_all([]), NpmAuditDataV2::default());
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_workspaces() -> Result<(), Error> {
+        let mut merged: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2_workspace_a.json"))?;
+        let other: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2_workspace_b.json"))?;
+        merged.merge(other);
+        assert_eq!(
+            merged.vulnerabilities.keys().collect::<Vec<_>>(),
+            vec!["debug", "lodash", "lodash.template", "minimist", "optimist"]
+        );
+        let lodash = &merged.vulnerabilities["lodash"];
+        assert_eq!(lodash.severity, Severity::Critical);
+        assert!(lodash.is_direct);
+        assert_eq!(lodash.range, "<=4.17.18");
+        let sources: Vec<u64> = lodash
+            .via
+            .iter()
+            .filter_map(|via| match via {
+                Vulnerability::Full { source, .. } => Some(*source),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(sources, vec![1068818, 1070253, 1070369]);
+        assert_eq!(lodash.effects, vec!["lodash.template"]);
+        assert_eq!(
+            lodash.nodes,
+            vec!["packages/b/node_modules/lodash", "node_modules/lodash"]
+        );
+        let counts = &merged.metadata.vulnerabilities;
+        assert_eq!(
+            (
+                counts.info,
+                counts.low,
+                counts.moderate,
+                counts.high,
+                counts.critical
+            ),
+            (0, 1, 2, 0, 2)
+        );
+        assert_eq!(counts.total, 5);
+        assert_eq!(merged.metadata.dependencies.total, 25);
+        assert_eq!(merged.metadata.dependencies.prod, 17);
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_by_cve() -> Result<(), Error> {
+        let v1: NpmAuditDataV1 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v1_shared_cve.json"))?;
+        for cve in ["CVE-2021-23337", "cve-2021-23337", "2021-23337"] {
+            let ids: Vec<u64> = v1.find_by_cve(cve).iter().map(|a| a.id).collect();
+            assert_eq!(ids, vec![1070355, 1070356]);
+        }
+        assert!(v1.find_by_cve("CVE-2019-10744").is_empty());
+        assert!(v1.find_by_cve("not a cve").is_empty());
+        let v2: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2_cve_urls.json"))?;
+        let found = v2.find_by_cve("cve-2020-8203");
+        assert_eq!(found.len(), 1);
+        assert!(matches!(
+            found[0],
+            Vulnerability::Full {
+                source: 1070253,
+                ..
+            }
+        ));
+        assert_eq!(v2.find_by_cve("2020-7598").len(), 1);
+        assert!(v2.find_by_cve("CVE-2021-23337").is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_version_vulnerable() -> Result<(), Error> {
+        let data: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2_version_ranges.json"))?;
+        assert_eq!(data.is_version_vulnerable("lodash", "4.17.20"), Some(true));
+        assert_eq!(data.is_version_vulnerable("lodash", "4.17.21"), Some(false));
+        assert_eq!(data.is_version_vulnerable("debug", "3.2.6"), Some(true));
+        assert_eq!(data.is_version_vulnerable("debug", "3.2.7"), Some(false));
+        assert_eq!(data.is_version_vulnerable("minimist", "0.2.3"), Some(true));
+        assert_eq!(data.is_version_vulnerable("minimist", "1.2.5"), Some(true));
+        assert_eq!(data.is_version_vulnerable("minimist", "0.2.4"), Some(false));
+        assert_eq!(data.is_version_vulnerable("minimist", "1.2.6"), Some(false));
+        assert_eq!(data.is_version_vulnerable("left-pad", "1.3.0"), None);
+        assert_eq!(data.is_version_vulnerable("internal-lib", "1.0.0"), None);
+        assert_eq!(data.is_version_vulnerable("lodash", "latest"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_module_path_whitespace() -> Result<(), Error> {
+        let expected = vec!["a", "b", "c"];
+        for path in ["a>b>c", "a > b > c", " a >b>  c "] {
+            assert_eq!(
+                deserialize_module_path(serde_json::Value::from(path))?,
+                expected
+            );
+        }
+        assert_eq!(
+            deserialize_module_path_vec(serde_json::json!(["a > b", "c>d"]))?,
+            vec![vec!["a", "b"], vec!["c", "d"]]
+        );
+        assert_eq!(split_module_path("a.b.c", "."), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_filtered_by_severity() -> Result<(), Error> {
+        let data: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2_cvss.json"))?;
+        let dependencies = data.metadata.dependencies.clone();
+        let filtered = data.clone().filtered_by_severity(Severity::High);
+        assert_eq!(
+            filtered.vulnerabilities.keys().collect::<Vec<_>>(),
+            vec!["chokidar", "glob-parent", "lodash", "watchpack-chokidar2"]
+        );
+        let counts = &filtered.metadata.vulnerabilities;
+        assert_eq!(
+            (
+                counts.info,
+                counts.low,
+                counts.moderate,
+                counts.high,
+                counts.critical
+            ),
+            (0, 0, 0, 3, 1)
+        );
+        assert_eq!(counts.total, 4);
+        assert_eq!(filtered.metadata.dependencies, dependencies);
+        let unfiltered = data.clone().filtered_by_severity(Severity::None);
+        assert_eq!(unfiltered.vulnerabilities, data.vulnerabilities);
+        assert_eq!(
+            unfiltered.metadata.vulnerabilities,
+            data.metadata.vulnerabilities
+        );
+        let critical = data.clone().filtered_by_severity(Severity::Critical);
+        assert_eq!(critical.metadata.vulnerabilities.total, 1);
+        let mut moderate_chokidar = data;
+        if let Some(chokidar) = moderate_chokidar.vulnerabilities.get_mut("chokidar") {
+            chokidar.severity = Severity::Moderate;
+        }
+        let filtered = moderate_chokidar.filtered_by_severity(Severity::High);
+        assert!(!filtered.vulnerabilities.contains_key("chokidar"));
+        assert_eq!(
+            filtered.vulnerabilities["glob-parent"].effects,
+            vec!["watchpack-chokidar2"]
+        );
+        assert_eq!(
+            filtered.vulnerabilities["watchpack-chokidar2"]
+                .via
+                .iter()
+                .map(Vulnerability::name)
+                .collect::<Vec<_>>(),
+            vec!["glob-parent"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_safely_fixable_count() -> Result<(), Error> {
+        let data: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2_fix_kinds.json"))?;
+        assert!(matches!(
+            data.vulnerabilities["lodash"].fix_available,
+            Fix::BoolOnly(true)
+        ));
+        assert!(matches!(
+            data.vulnerabilities["minimist"].fix_available,
+            Fix::Full {
+                is_sem_ver_major: false,
+                ..
+            }
+        ));
+        assert_eq!(data.safely_fixable_count(), 2);
+        assert_eq!(data.breaking_fix_count(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_cvss() -> Result<(), Error> {
+        let data: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2_cvss.json"))?;
+        let cvss = |name: &str| data.vulnerabilities[name].via.iter().find_map(|v| v.cvss());
+        let glob_parent = cvss("glob-parent").expect("glob-parent has a CVSS score");
+        assert_eq!(glob_parent.score, 7.5);
+        assert_eq!(
+            glob_parent.vector_string.as_deref(),
+            Some("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:N/I:N/A:H")
+        );
+        let minimist = cvss("minimist").expect("minimist has a CVSS score");
+        assert_eq!(minimist.vector_string, None);
+        assert_eq!(cvss("chokidar"), None);
+        let data: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2.json"))?;
+        assert!(data
+            .vulnerabilities
+            .values()
+            .flat_map(|p| p.via.iter())
+            .all(|v| v.cvss().is_none()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_audit_with_value() -> Result<(), Error> {
+        let json = include_str!("../fixtures/npm_audit_v2.json");
+        let (data, value) = parse_audit_with_value(json)?;
+        assert_eq!(data, parse_audit_str(json, 2)?);
+        let NpmAuditData::Version2(v2) = &data else {
+            panic!("expected a version 2 report");
+        };
+        assert_eq!(
+            value["metadata"]["vulnerabilities"]["total"],
+            serde_json::Value::from(v2.metadata.vulnerabilities.total)
+        );
+        assert_eq!(
+            value["vulnerabilities"].as_object().map(|o| o.len()),
+            Some(v2.vulnerabilities.len())
+        );
+        let (data, value) = parse_audit_with_value(include_str!("../fixtures/npm_audit_v1.json"))?;
+        assert!(matches!(data, NpmAuditData::Version1(_)));
+        assert_eq!(detect_report_format_value(&value), Some(1));
+        assert_eq!(detect_report_format_value(&serde_json::Value::Null), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_url_domain() -> Result<(), Error> {
+        let mut data: NpmAuditDataV1 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v1.json"))?;
+        let mut advisory = data.advisories["1070355"].clone();
+        assert_eq!(advisory.url_domain(), Some("github.com"));
+        for (url, domain) in [
+            (
+                "https://www.npmjs.com/advisories/1179",
+                Some("www.npmjs.com"),
+            ),
+            (
+                "https://user@nvd.nist.gov:443/vuln?id=1",
+                Some("nvd.nist.gov"),
+            ),
+            ("http://[::1]:8080/", Some("[::1]")),
+            ("not a url", None),
+            ("https:///path", None),
+        ] {
+            advisory.url = url.to_string();
+            assert_eq!(advisory.url_domain(), domain, "{}", url);
+        }
+        data.advisories
+            .get_mut("1067342")
+            .expect("fixture contains advisory 1067342")
+            .url = "https://evil.example/advisory".to_string();
+        data.advisories
+            .get_mut("1068263")
+            .expect("fixture contains advisory 1068263")
+            .url = "https://WWW.NPMJS.COM/advisories/1".to_string();
+        let unexpected: Vec<u64> = data
+            .unexpected_url_domains(&["github.com", "npmjs.com", "nvd.nist.gov"])
+            .iter()
+            .map(|a| a.id)
+            .collect();
+        assert_eq!(unexpected, vec![1005365, 1067342]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_v1_metadata() -> Result<(), Error> {
+        let data: NpmAuditDataV1 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v1.json"))?;
+        assert_eq!(data.metadata.dependencies, 173);
+        assert_eq!(data.metadata.dev_dependencies, 412);
+        assert_eq!(data.metadata.optional_dependencies, 8);
+        assert_eq!(data.metadata.total_dependencies, 585);
+        assert_eq!(data.metadata.vulnerabilities.high, 2);
+        Ok(())
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_advisory_time_chrono() -> Result<(), Error> {
+        let data: NpmAuditDataV1 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v1.json"))?;
+        let advisory = &data.advisories["1070355"];
+        assert_eq!(
+            advisory.created_chrono(),
+            chrono::DateTime::parse_from_rfc3339("2021-02-15T20:20:06Z")
+                .expect("valid timestamp")
+                .with_timezone(&chrono::Utc)
+        );
+        assert_eq!(
+            advisory.created_chrono().timestamp(),
+            advisory.created.unix_timestamp()
+        );
+        assert_eq!(
+            advisory.updated_chrono().map(|t| t.timestamp()),
+            advisory.updated.map(|t| t.unix_timestamp())
+        );
+        assert_eq!(advisory.deleted_chrono(), None);
+        let json = serde_json::to_value(advisory)?;
+        assert_eq!(json["created"], "2021-02-15T20:20:06Z");
+        Ok(())
+    }
+
+    #[test]
+    fn test_count_by_access() -> Result<(), Error> {
+        let data: NpmAuditDataV1 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v1_mixed_access.json"))?;
+        assert_eq!(
+            data.count_by_access(),
+            BTreeMap::from([
+                ("internal".to_string(), 1),
+                ("private".to_string(), 2),
+                ("public".to_string(), 2),
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_audit_reader() -> Result<(), Error> {
+        let v2 = include_str!("../fixtures/npm_audit_v2.json");
+        let data = parse_audit_reader(std::io::BufReader::new(v2.as_bytes()), 2)?;
+        assert_eq!(data.package_severities().len(), 6);
+        let data = parse_audit_reader(include_str!("../fixtures/npm_audit_v1.json").as_bytes(), 1)?;
+        assert!(matches!(data, NpmAuditData::Version1(_)));
+        assert!(matches!(
+            parse_audit_reader(&v2.as_bytes()[..v2.len() / 2], 2),
+            Err(Error::TruncatedOutput)
+        ));
+        let mut invalid_utf8 = v2.as_bytes().to_vec();
+        let pos = v2.find("lodash").expect("fixture mentions lodash");
+        invalid_utf8[pos] = 0xff;
+        assert!(matches!(
+            parse_audit_reader(invalid_utf8.as_slice(), 2),
+            Err(Error::SerdePathError(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_audit_reader_empty_object() -> Result<(), Error> {
+        let json_str = include_str!("../fixtures/npm_audit_empty_object.json");
+        for report_format in [1, 2] {
+            assert_eq!(
+                parse_audit_reader(json_str.as_bytes(), report_format)?,
+                parse_audit_str(json_str, report_format)?
+            );
+        }
+        assert_eq!(
+            parse_audit_reader(&b" {\r\n\t} \n"[..], 2)?,
+            NpmAuditData::Version2(NpmAuditDataV2::default())
+        );
+        assert!(matches!(
+            parse_audit_reader(&b"{} {}"[..], 2),
+            Err(Error::SerdeJsonError(_))
+        ));
+        assert!(matches!(
+            parse_audit_reader(&b"  {"[..], 2),
+            Err(Error::TruncatedOutput)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolved_advisory_ids() -> Result<(), Error> {
+        let data: NpmAuditDataV1 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v1_actions.json"))?;
+        assert_eq!(
+            data.resolved_advisory_ids(),
+            BTreeSet::from([1001001, 1001003, 1070355])
+        );
+        let unresolved: Vec<u64> = data
+            .advisories
+            .values()
+            .map(|a| a.id)
+            .filter(|id| !data.resolved_advisory_ids().contains(id))
+            .collect();
+        assert_eq!(unresolved, vec![1001002, 1001004]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_packages_affected_by() -> Result<(), Error> {
+        let data: NpmAuditDataV1 = serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v1_multi_package_advisory.json"
+        ))?;
+        assert_eq!(
+            data.packages_affected_by(1070355),
+            vec!["lodash".to_string(), "lodash-es".to_string()]
+        );
+        assert!(data.packages_affected_by(1).is_empty());
+        Ok(())
+    }
+
+    /// this test requires a package.json and package-lock.json in the main crate
+    /// directory (working dir of the tests)
+    #[test]
+    fn test_run_npm_audit_timed() -> Result<(), Error> {
+        let result = AuditCommand::new().timed(true).run()?;
+        assert!(result.timings.is_some());
+        let result = AuditCommand::new().run()?;
+        assert!(result.timings.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_v1() -> Result<(), Error> {
+        let data: NpmAuditDataV1 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v1.json"))?;
+        assert_eq!(data.advisories.len(), 4);
+        assert_eq!(data.actions.len(), 5);
+        let lodash = &data.advisories["1070355"];
+        assert_eq!(lodash.module_name.as_deref(), Some("lodash"));
+        assert_eq!(lodash.vulnerable_versions.as_deref(), Some("<4.17.21"));
+        assert_eq!(lodash.patched_versions.as_deref(), Some(">=4.17.21"));
+        assert_eq!(
+            lodash.github_advisory_id.as_deref(),
+            Some("GHSA-35jh-r3h4-6jhm")
+        );
+        let json = serde_json::to_value(lodash)?;
+        assert_eq!(json["module_name"], "lodash");
+        Ok(())
+    }
+
+    #[test]
+    fn test_advisory_found_by_object() -> Result<(), Error> {
+        let data: NpmAuditDataV1 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v1_found_by.json"))?;
+        let lodash = &data.advisories["1070355"];
+        assert_eq!(lodash.found_by.as_deref(), Some("Snyk Security Team"));
+        assert_eq!(lodash.reported_by.as_deref(), Some("Anonymous"));
+        let minimist = &data.advisories["1067342"];
+        assert_eq!(minimist.found_by.as_deref(), Some("Jane Doe"));
+        assert_eq!(minimist.reported_by.as_deref(), Some("Jane Doe"));
+        let glob_parent = &data.advisories["1068263"];
+        assert_eq!(glob_parent.found_by, None);
+        assert_eq!(glob_parent.reported_by, None);
+        let reparsed: NpmAuditDataV1 = serde_json::from_str(&serde_json::to_string(&data)?)?;
+        assert_eq!(
+            reparsed.advisories["1070355"].found_by.as_deref(),
+            Some("Snyk Security Team")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_v1_null_finding_version() -> Result<(), Error> {
+        let data: NpmAuditDataV1 = serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v1_null_finding_version.json"
+        ))?;
+        let findings = &data.advisories["1067342"].findings;
+        assert_eq!(findings[0].version.as_deref(), Some("0.0.8"));
+        assert_eq!(findings[1].version, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fix_within_range() -> Result<(), Error> {
+        let data: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2_fixes.json"))?;
+        let axios = &data.vulnerabilities["axios"];
+        assert_eq!(axios.fix_within_range("^0.28.0"), Some(true));
+        assert_eq!(axios.fix_within_range("~0.27.2"), Some(false));
+        assert_eq!(axios.fix_within_range("not a range"), None);
+        let semver = &data.vulnerabilities["semver"];
+        assert_eq!(semver.fix_within_range("^5.0.0"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_triage() -> Result<(), Error> {
+        let data: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2_fixes.json"))?;
+        assert_eq!(
+            data.triage(),
+            Triage {
+                fix_now: vec!["axios".to_string(), "semver".to_string()],
+                plan: vec!["express".to_string(), "webpack-dev-server".to_string()],
+                investigate: vec!["request".to_string()],
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit_command_lockfile() -> Result<(), Error> {
+        let dir = crate::test_dir("lockfile");
+        let lockfile = dir.join("package-lock.json");
+        std::fs::write(&lockfile, "{}")?;
+        let cmd = AuditCommand::new().lockfile(&lockfile).command()?;
+        assert_eq!(cmd.get_current_dir(), Some(dir.as_path()));
+        let cmd = AuditCommand::new().command()?;
+        assert_eq!(cmd.get_current_dir(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit_command_invalid_lockfile() -> Result<(), Error> {
+        let dir = crate::test_dir("invalid-lockfile");
+        let yarn_lock = dir.join("yarn.lock");
+        std::fs::write(&yarn_lock, "")?;
+        assert!(matches!(
+            AuditCommand::new().lockfile(&yarn_lock).command(),
+            Err(Error::InvalidLockfile(_))
+        ));
+        assert!(matches!(
+            AuditCommand::new()
+                .lockfile(dir.join("npm-shrinkwrap.json"))
+                .command(),
+            Err(Error::InvalidLockfile(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_most_recent_advisory() -> Result<(), Error> {
+        let mut data: NpmAuditDataV1 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v1.json"))?;
+        // glob-parent and left-pad were created at the same time
+        assert_eq!(data.most_recent_advisory().map(|a| a.id), Some(1068263));
+        data.advisories.remove("1068263");
+        assert_eq!(data.most_recent_advisory().map(|a| a.id), Some(1005365));
+        data.advisories.clear();
+        assert!(data.most_recent_advisory().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit_command_report_format() -> Result<(), Error> {
+        let report_format = |command: AuditCommand| -> Result<Option<u8>, Error> {
+            Ok(command.report_format_for(command.probed_npm_version()?.as_ref()))
+        };
+        let version = versions::Versioning::new("6.14.18").expect("valid version");
+        assert_eq!(
+            report_format(AuditCommand::new().npm_version(version))?,
+            Some(1)
+        );
+        let version = versions::Versioning::new("8.19.4").expect("valid version");
+        assert_eq!(
+            report_format(AuditCommand::new().npm_version(version))?,
+            Some(2)
+        );
+        let version = versions::Versioning::new("8.19.4").expect("valid version");
+        assert_eq!(
+            report_format(
+                AuditCommand::new()
+                    .npm_version(version)
+                    .force_report_format(1)
+            )?,
+            Some(1)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit_command_offline() -> Result<(), Error> {
+        assert_eq!(
+            AuditCommand::new().offline(true).args()?,
+            vec!["audit", "--json", "--offline"]
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_is_offline_cache_miss() {
+        use std::os::unix::process::ExitStatusExt;
+        let output = std::process::Output {
+            status: std::process::ExitStatus::from_raw(1 << 8),
+            stdout: br#"{"error":{"code":"ENOTCACHED","summary":"request to https://registry.npmjs.org/-/npm/v1/security/advisories/bulk failed: cache mode is 'only-if-cached' but no cached response is available."}}"#.to_vec(),
+            stderr: b"npm error code ENOTCACHED\n".to_vec(),
+        };
+        assert!(is_offline_cache_miss(&output));
+        let output = std::process::Output {
+            stdout: b"{}".to_vec(),
+            stderr: vec![],
+            ..output
+        };
+        assert!(!is_offline_cache_miss(&output));
+    }
+
+    #[test]
+    fn test_audit_command_dry_run_force() -> Result<(), Error> {
+        assert_eq!(
+            AuditCommand::new().dry_run_force(true).args()?,
+            vec!["audit", "fix", "--dry-run", "--force", "--json"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_fix_preview() -> Result<(), Error> {
+        let json_str = include_str!("../fixtures/npm_audit_fix_dry_run_force.json");
+        let (data, preview) = parse_fix_preview(json_str)?;
+        assert_eq!(
+            preview,
+            AuditFixPreview {
+                added: 3,
+                removed: 1,
+                changed: 5,
+                audited: 476,
+                funding: 12,
+                raw: serde_json::from_str(json_str)?,
+            }
+        );
+        assert_eq!(
+            data.package_severities().keys().collect::<Vec<_>>(),
+            vec!["minimist", "optimist"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_fix_preview_schema_drift() -> Result<(), Error> {
+        let (data, preview) =
+            parse_fix_preview(r#"{"added":1,"changed":2,"audited":3,"newField":{"nested":true}}"#)?;
+        assert_eq!(preview.added, 1);
+        assert_eq!(preview.removed, 0);
+        assert_eq!(preview.funding, 0);
+        assert_eq!(
+            preview.raw["newField"]["nested"],
+            serde_json::Value::Bool(true)
+        );
+        assert!(data.package_severities().is_empty());
+        let reparsed: AuditFixPreview = serde_json::from_str(&serde_json::to_string(&preview)?)?;
+        assert_eq!(reparsed.added, 1);
+        assert_eq!(reparsed.raw, serde_json::Value::Null);
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit_command_cache_dir() -> Result<(), Error> {
+        assert_eq!(
+            AuditCommand::new().cache_dir("/tmp/npm-cache").args()?,
+            vec!["audit", "--json", "--cache", "/tmp/npm-cache"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit_command_legacy_peer_deps() -> Result<(), Error> {
+        assert_eq!(
+            AuditCommand::new().legacy_peer_deps(true).args()?,
+            vec!["audit", "--json", "--legacy-peer-deps"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit_command_node_modules_missing() -> Result<(), Error> {
+        let dir = crate::test_dir("node-modules-missing");
+        let lockfile = dir.join("package-lock.json");
+        std::fs::write(&lockfile, "{}")?;
+        let cmd = AuditCommand::new().lockfile(&lockfile).command()?;
+        assert_eq!(
+            cmd.get_args().last().and_then(|a| a.to_str()),
+            Some("--package-lock-only")
+        );
+        assert!(matches!(
+            AuditCommand::new()
+                .lockfile(&lockfile)
+                .require_node_modules(true)
+                .command(),
+            Err(Error::NodeModulesMissing(_))
+        ));
+        std::fs::create_dir(dir.join("node_modules"))?;
+        let cmd = AuditCommand::new()
+            .lockfile(&lockfile)
+            .require_node_modules(true)
+            .command()?;
+        assert!(!cmd.get_args().any(|a| a == "--package-lock-only"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_fix_sem_ver_major_casing() -> Result<(), Error> {
+        for json in [
+            include_str!("../fixtures/npm_audit_v2_is_sem_ver_major.json"),
+            include_str!("../fixtures/npm_audit_v2_is_semver_major.json"),
+        ] {
+            let data: NpmAuditDataV2 = serde_json::from_str(json)?;
+            assert!(data.vulnerabilities["express"]
+                .fix_available
+                .requires_major());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_audit_metadata_only() -> Result<(), Error> {
+        match parse_audit_metadata_only(include_str!("../fixtures/npm_audit_v2.json"))? {
+            AuditMetadata::Version2(metadata) => {
+                assert_eq!(metadata.vulnerabilities.total, 6);
+                assert_eq!(metadata.dependencies.total, 471);
+            }
+            AuditMetadata::Version1(_) => panic!("expected version 2 metadata"),
+        }
+        match parse_audit_metadata_only(include_str!("../fixtures/npm_audit_v1.json"))? {
+            AuditMetadata::Version1(metadata) => {
+                assert_eq!(metadata.vulnerabilities.high, 2);
+                assert_eq!(metadata.total_dependencies, 585);
+            }
+            AuditMetadata::Version2(_) => panic!("expected version 1 metadata"),
+        }
+        assert!(parse_audit_metadata_only(r#"{"vulnerabilities":{}}"#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_has_unfixed_cve_v1() -> Result<(), Error> {
+        let data = NpmAuditData::Version1(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v1.json"
+        ))?);
+        // lodash is fixed by install actions on all paths
+        assert!(!data.has_unfixed_cve("CVE-2021-23337"));
+        // one of the minimist paths needs a review
+        assert!(data.has_unfixed_cve("cve-2020-7598"));
+        // glob-parent only has a review action
+        assert!(data.has_unfixed_cve("2020-28469"));
+        assert!(!data.has_unfixed_cve("CVE-1999-0001"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_has_unfixed_cve_v2() -> Result<(), Error> {
+        let data = NpmAuditData::Version2(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v2_cve_urls.json"
+        ))?);
+        assert!(!data.has_unfixed_cve("CVE-2020-8203"));
+        assert!(data.has_unfixed_cve("CVE-2020-7598"));
+        assert!(!data.has_unfixed_cve("not a cve"));
+        Ok(())
+    }
+
+    /// this test requires npm to be installed
+    #[cfg(feature = "tarball")]
+    #[test]
+    fn test_run_npm_audit_tarball() -> Result<(), Error> {
+        let dir = crate::test_dir("audit-tarball");
+        let tarball = crate::tarball::test::create_tarball(&dir)?;
+        let result = AuditCommand::new().tarball(&tarball).run()?;
+        assert_eq!(result.requirement, IndicatedUpdateRequirement::UpToDate);
+        Ok(())
+    }
+
+    #[cfg(all(unix, feature = "tarball"))]
+    #[test]
+    fn test_audit_tarball_lock_failed() -> Result<(), Error> {
+        let options = fake_npm_options(
+            "audit-tarball-lock-failed",
+            r#"if [ "$1" = "install" ]; then echo "npm ERR! code E404" >&2; exit 1; fi
+cat report.json; exit 1"#,
+            &[("report.json", include_str!("../fixtures/npm_audit_v2.json"))],
+        )?;
+        let tarball = crate::tarball::test::create_tarball(
+            options
+                .working_dir
+                .as_deref()
+                .expect("fake npm options have a working directory"),
+        )?;
+        match AuditCommand::new().options(options).tarball(&tarball).run() {
+            Err(Error::NpmCommandFailed { status, stderr }) => {
+                assert_eq!(status, Some(1));
+                assert_eq!(stderr, "npm ERR! code E404\n");
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
         Ok(())
     }
 }
@@ -0,0 +1,230 @@
+//! This combines the output of npm-audit and npm-outdated
+//!
+//! keyed by package name, the name is the only thing both reports have in
+//! common
+
+use crate::audit::{NpmAuditData, Severity};
+use crate::outdated::NpmOutdatedData;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The combined status of a package from npm-audit and npm-outdated
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PackageHealth {
+    /// does npm-outdated list the package
+    pub outdated: bool,
+    /// does npm-audit list the package as vulnerable
+    pub vulnerable: bool,
+    /// the worst severity of the vulnerabilities affecting the package
+    pub worst_severity: Option<Severity>,
+}
+
+/// the combined status of every package appearing in either report, by
+/// package name
+pub fn package_health(
+    audit: &NpmAuditData,
+    outdated: &NpmOutdatedData,
+) -> BTreeMap<String, PackageHealth> {
+    let mut health: BTreeMap<String, PackageHealth> = outdated
+        .0
+        .keys()
+        .map(|name| {
+            (
+                name.to_string(),
+                PackageHealth {
+                    outdated: true,
+                    vulnerable: false,
+                    worst_severity: None,
+                },
+            )
+        })
+        .collect();
+    for (name, severity) in audit.package_severities() {
+        let entry = health.entry(name).or_insert(PackageHealth {
+            outdated: false,
+            vulnerable: false,
+            worst_severity: None,
+        });
+        entry.vulnerable = true;
+        entry.worst_severity = Some(severity);
+    }
+    health
+}
+
+/// A package which is both outdated and vulnerable with a fix available
+///
+/// returned by [priority_upgrades]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PriorityUpgrade {
+    /// the package name
+    pub name: String,
+    /// the installed version according to npm outdated
+    pub current: Option<String>,
+    /// the version npm outdated considers wanted
+    pub wanted: Option<String>,
+    /// the latest version according to npm outdated
+    pub latest: Option<String>,
+    /// the worst severity of the vulnerabilities affecting the package
+    pub worst_severity: Severity,
+}
+
+/// the names of the vulnerable packages with at least one vulnerability
+/// npm knows a fix for
+///
+/// for report version 1 an advisory counts as fixable if an install or
+/// update action resolves it without any review action
+fn packages_with_fix(audit: &NpmAuditData) -> BTreeSet<&str> {
+    match audit {
+        NpmAuditData::Version1(data) => data
+            .advisories
+            .values()
+            .filter(|a| data.is_fixed_by_actions(a.id))
+            .filter_map(|a| a.package_name())
+            .collect(),
+        NpmAuditData::Version2(data) => data
+            .vulnerabilities
+            .iter()
+            .filter(|(_, p)| p.fix_available.is_available())
+            .map(|(name, _)| name.as_str())
+            .collect(),
+    }
+}
+
+/// the packages which are outdated and vulnerable with a fix available,
+/// most severe first and by name within the same severity
+///
+/// these are the upgrades which should be done first
+pub fn priority_upgrades(audit: &NpmAuditData, outdated: &NpmOutdatedData) -> Vec<PriorityUpgrade> {
+    let with_fix = packages_with_fix(audit);
+    let mut upgrades: Vec<PriorityUpgrade> = audit
+        .package_severities()
+        .into_iter()
+        .filter(|(name, _)| with_fix.contains(name.as_str()))
+        .filter_map(|(name, worst_severity)| {
+            let status = outdated.0.get(&name)?;
+            Some(PriorityUpgrade {
+                current: status.current.to_owned(),
+                wanted: status.wanted.to_owned(),
+                latest: status.latest.to_owned(),
+                name,
+                worst_severity,
+            })
+        })
+        .collect();
+    upgrades.sort_by(|a, b| {
+        b.worst_severity
+            .cmp(&a.worst_severity)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    upgrades
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn test_package_health() -> Result<(), Error> {
+        let audit = NpmAuditData::Version2(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v2.json"
+        ))?);
+        let outdated: NpmOutdatedData =
+            serde_json::from_str(include_str!("../fixtures/npm_outdated_git_dependency.json"))?;
+        let health = package_health(&audit, &outdated);
+        assert_eq!(
+            health.keys().collect::<Vec<_>>(),
+            vec![
+                "chokidar",
+                "glob-parent",
+                "lodash",
+                "minimist",
+                "my-fork",
+                "optimist",
+                "watchpack-chokidar2"
+            ]
+        );
+        assert_eq!(
+            health["lodash"],
+            PackageHealth {
+                outdated: true,
+                vulnerable: true,
+                worst_severity: Some(Severity::Critical),
+            }
+        );
+        assert_eq!(
+            health["my-fork"],
+            PackageHealth {
+                outdated: true,
+                vulnerable: false,
+                worst_severity: None,
+            }
+        );
+        assert_eq!(
+            health["minimist"],
+            PackageHealth {
+                outdated: false,
+                vulnerable: true,
+                worst_severity: Some(Severity::Moderate),
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_package_health_v1() -> Result<(), Error> {
+        let audit = NpmAuditData::Version1(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v1.json"
+        ))?);
+        let outdated = NpmOutdatedData(BTreeMap::new());
+        let health = package_health(&audit, &outdated);
+        assert_eq!(health.len(), 4);
+        assert_eq!(health["lodash"].worst_severity, Some(Severity::High));
+        Ok(())
+    }
+
+    #[test]
+    fn test_priority_upgrades() -> Result<(), Error> {
+        let audit = NpmAuditData::Version2(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v2.json"
+        ))?);
+        // lodash has a fix, minimist has none and my-fork is not vulnerable
+        let outdated: NpmOutdatedData = serde_json::from_str(
+            r#"{
+                "lodash": {"current": "4.17.20", "wanted": "4.17.21", "latest": "4.17.21", "location": "node_modules/lodash", "type": "dependencies"},
+                "minimist": {"current": "0.0.8", "wanted": "0.0.10", "latest": "1.2.8", "location": "node_modules/optimist/node_modules/minimist", "type": "dependencies"},
+                "my-fork": {"current": "1.0.0", "location": "node_modules/my-fork", "type": "dependencies"}
+            }"#,
+        )?;
+        assert_eq!(
+            priority_upgrades(&audit, &outdated),
+            vec![PriorityUpgrade {
+                name: "lodash".to_string(),
+                current: Some("4.17.20".to_string()),
+                wanted: Some("4.17.21".to_string()),
+                latest: Some("4.17.21".to_string()),
+                worst_severity: Severity::Critical,
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_priority_upgrades_v1() -> Result<(), Error> {
+        let audit = NpmAuditData::Version1(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v1.json"
+        ))?);
+        // lodash is fixed by install actions, minimist needs a review
+        let outdated: NpmOutdatedData = serde_json::from_str(
+            r#"{
+                "lodash": {"current": "4.17.20", "wanted": "4.17.21", "latest": "4.17.21", "location": "node_modules/lodash", "type": "dependencies"},
+                "minimist": {"current": "0.0.8", "wanted": "0.0.10", "latest": "1.2.8", "location": "node_modules/minimist", "type": "dependencies"}
+            }"#,
+        )?;
+        let upgrades = priority_upgrades(&audit, &outdated);
+        assert_eq!(upgrades.len(), 1);
+        assert_eq!(upgrades[0].name, "lodash");
+        assert_eq!(upgrades[0].current.as_deref(), Some("4.17.20"));
+        assert_eq!(upgrades[0].worst_severity, Severity::High);
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+//! This converts npm-audit reports to CycloneDX VEX documents
+//!
+//! [CycloneDX VEX](https://cyclonedx.org/capabilities/vex/)
+
+use crate::audit::{extract_cves, NpmAuditData, Severity, Vulnerability};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// The CycloneDX specification version of the generated documents
+pub const CYCLONEDX_SPEC_VERSION: &str = "1.4";
+
+/// the CycloneDX rating severity for an npm severity
+fn cyclonedx_severity(severity: &Severity) -> &'static str {
+    match severity {
+        Severity::None => "none",
+        Severity::Info => "info",
+        Severity::Low => "low",
+        Severity::Moderate => "medium",
+        Severity::High => "high",
+        Severity::Critical => "critical",
+    }
+}
+
+/// the package URL of an npm package
+fn npm_purl(package: &str) -> String {
+    format!("pkg:npm/{}", package.replace('@', "%40"))
+}
+
+/// the source of an advisory identifier as a CycloneDX source object
+fn source_for(id: &str, url: &str) -> Value {
+    if id.starts_with("CVE-") {
+        json!({ "name": "NVD", "url": format!("https://nvd.nist.gov/vuln/detail/{}", id) })
+    } else if id.starts_with("GHSA-") {
+        json!({ "name": "GitHub", "url": url })
+    } else {
+        json!({ "name": "npm", "url": url })
+    }
+}
+
+/// a single CycloneDX vulnerability entry
+fn vulnerability(
+    id: &str,
+    url: &str,
+    description: &str,
+    severity: &Severity,
+    package: &str,
+) -> Value {
+    json!({
+        "bom-ref": format!("{}/{}", id, package),
+        "id": id,
+        "source": source_for(id, url),
+        "ratings": [{ "severity": cyclonedx_severity(severity) }],
+        "description": description,
+        "affects": [{ "ref": npm_purl(package) }],
+    })
+}
+
+/// the identifier from a GitHub advisory URL
+fn ghsa_from_url(url: &str) -> Option<&str> {
+    url.rsplit('/').next().filter(|s| s.starts_with("GHSA-"))
+}
+
+impl NpmAuditData {
+    /// a minimal CycloneDX VEX document listing the vulnerabilities in this
+    /// report
+    ///
+    /// the id of each vulnerability is a CVE identifier if one is known,
+    /// otherwise the GitHub advisory id or the npm advisory id. The affected
+    /// components are referenced by their package URL (pkg:npm/...).
+    /// Vulnerabilities are sorted by their bom-ref so the output is
+    /// deterministic.
+    pub fn to_cyclonedx_vex(&self) -> Value {
+        let mut vulnerabilities = BTreeMap::new();
+        match self {
+            NpmAuditData::Version1(data) => {
+                for advisory in data.advisories.values() {
+                    let Some(package) = advisory.package_name() else {
+                        continue;
+                    };
+                    let id = advisory
+                        .cve_ids()
+                        .first()
+                        .map(|c| c.to_string())
+                        .or_else(|| advisory.github_advisory_id.clone())
+                        .unwrap_or_else(|| advisory.id.to_string());
+                    let v = vulnerability(
+                        &id,
+                        &advisory.url,
+                        &advisory.title,
+                        &advisory.severity,
+                        package,
+                    );
+                    vulnerabilities.insert(v["bom-ref"].to_string(), v);
+                }
+            }
+            NpmAuditData::Version2(data) => {
+                for (package, vulnerable) in &data.vulnerabilities {
+                    for via in &vulnerable.via {
+                        let Vulnerability::Full {
+                            source,
+                            title,
+                            url,
+                            severity,
+                            ..
+                        } = via
+                        else {
+                            continue;
+                        };
+                        let id = extract_cves(url)
+                            .first()
+                            .map(|c| c.to_string())
+                            .or_else(|| ghsa_from_url(url).map(|s| s.to_string()))
+                            .unwrap_or_else(|| source.to_string());
+                        let v = vulnerability(&id, url, title, severity, package);
+                        vulnerabilities.insert(v["bom-ref"].to_string(), v);
+                    }
+                }
+            }
+        }
+        json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": CYCLONEDX_SPEC_VERSION,
+            "version": 1,
+            "vulnerabilities": vulnerabilities.into_values().collect::<Vec<_>>(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn test_to_cyclonedx_vex_v2() -> Result<(), Error> {
+        let data = NpmAuditData::Version2(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v2.json"
+        ))?);
+        let expected: Value =
+            serde_json::from_str(include_str!("../fixtures/cyclonedx_vex_v2.json"))?;
+        assert_eq!(data.to_cyclonedx_vex(), expected);
+        Ok(())
+    }
+
+    /// checks the parts of the CycloneDX 1.4 schema that apply to the
+    /// fields we generate
+    #[test]
+    fn test_to_cyclonedx_vex_v1_schema() -> Result<(), Error> {
+        let data = NpmAuditData::Version1(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v1.json"
+        ))?);
+        let vex = data.to_cyclonedx_vex();
+        assert_eq!(vex["bomFormat"], "CycloneDX");
+        assert_eq!(vex["specVersion"], CYCLONEDX_SPEC_VERSION);
+        let vulnerabilities = vex["vulnerabilities"].as_array().unwrap();
+        assert_eq!(vulnerabilities.len(), 4);
+        let allowed_severities = [
+            "critical", "high", "medium", "low", "info", "none", "unknown",
+        ];
+        for v in vulnerabilities {
+            assert!(v["bom-ref"].is_string());
+            assert!(v["id"].is_string());
+            assert!(v["source"]["name"].is_string());
+            for rating in v["ratings"].as_array().unwrap() {
+                assert!(allowed_severities.contains(&rating["severity"].as_str().unwrap()));
+            }
+            for affected in v["affects"].as_array().unwrap() {
+                assert!(affected["ref"].as_str().unwrap().starts_with("pkg:npm/"));
+            }
+        }
+        let lodash = vulnerabilities
+            .iter()
+            .find(|v| v["affects"][0]["ref"] == "pkg:npm/lodash")
+            .unwrap();
+        assert_eq!(lodash["id"], "CVE-2021-23337");
+        assert_eq!(lodash["source"]["name"], "NVD");
+        Ok(())
+    }
+
+    /// validates the generated documents with the CycloneDX 1.4 model of
+    /// the cyclonedx-bom crate and checks nothing is lost in a round trip
+    /// through it
+    #[test]
+    fn test_to_cyclonedx_vex_validates() -> Result<(), Error> {
+        use cyclonedx_bom::prelude::{Bom, SpecVersion, Validate};
+
+        for data in [
+            NpmAuditData::Version1(serde_json::from_str(include_str!(
+                "../fixtures/npm_audit_v1.json"
+            ))?),
+            NpmAuditData::Version2(serde_json::from_str(include_str!(
+                "../fixtures/npm_audit_v2.json"
+            ))?),
+        ] {
+            let vex = data.to_cyclonedx_vex();
+            let json = serde_json::to_string(&vex)?;
+            let bom = Bom::parse_from_json_v1_4(json.as_bytes()).expect("CycloneDX 1.4 document");
+            let validation = bom.validate_version(SpecVersion::V1_4);
+            assert!(validation.passed(), "{:?}", validation);
+            let mut output = Vec::new();
+            bom.output_as_json_v1_4(&mut output)
+                .expect("serializable CycloneDX 1.4 document");
+            let round_trip: Value = serde_json::from_slice(&output)?;
+            assert_eq!(round_trip, vex);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+//! This interprets the exit codes of npm and produces exit codes for tools
+//! built on this crate
+//!
+//! npm audit and npm outdated both exit with 1 if they found something to
+//! report (vulnerabilities or outdated packages)
+
+use crate::audit::{NpmAuditData, Severity};
+use std::process::Output;
+
+/// What the exit code of an npm call means
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NpmExitClass {
+    /// npm exited with 0, nothing to report
+    Success,
+    /// npm exited with 1, for audit this means vulnerabilities were found,
+    /// for outdated that outdated packages were found
+    VulnerabilitiesFound,
+    /// npm exited with 2, the command line was invalid
+    UsageError,
+    /// any other exit code or no exit code at all (e.g. when npm was killed
+    /// by a signal)
+    Unknown,
+}
+
+/// classifies an exit code of npm as returned by [std::process::ExitStatus::code]
+pub fn classify_exit_code(code: Option<i32>) -> NpmExitClass {
+    match code {
+        Some(0) => NpmExitClass::Success,
+        Some(1) => NpmExitClass::VulnerabilitiesFound,
+        Some(2) => NpmExitClass::UsageError,
+        _ => NpmExitClass::Unknown,
+    }
+}
+
+/// did npm find something to report (vulnerabilities or outdated packages)
+/// according to its exit status
+///
+/// returns [crate::Error::NpmCommandFailed] with what npm printed on stderr
+/// if the exit status indicates an error instead of a report
+pub(crate) fn found_something(output: &Output) -> Result<bool, crate::Error> {
+    match classify_exit_code(output.status.code()) {
+        NpmExitClass::Success => Ok(false),
+        NpmExitClass::VulnerabilitiesFound => Ok(true),
+        NpmExitClass::UsageError | NpmExitClass::Unknown => Err(crate::Error::NpmCommandFailed {
+            status: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }),
+    }
+}
+
+/// the exit code returned by [exit_code] if no vulnerability meets the
+/// threshold
+pub const EXIT_CODE_PASS: i32 = 0;
+
+/// the exit code returned by [exit_code] if at least one vulnerability meets
+/// the threshold
+pub const EXIT_CODE_FAIL: i32 = 1;
+
+/// the process exit code for a report under a severity threshold
+///
+/// returns [EXIT_CODE_PASS] (0) if no vulnerable package in the report has a
+/// severity of at least fail_on and [EXIT_CODE_FAIL] (1) otherwise. Unlike
+/// [crate::outdated::IndicatedUpdateRequirement] which only reflects whether
+/// npm found anything this makes the threshold explicit.
+pub fn exit_code(data: &NpmAuditData, fail_on: Severity) -> i32 {
+    if data
+        .package_severities()
+        .values()
+        .any(|severity| *severity >= fail_on)
+    {
+        EXIT_CODE_FAIL
+    } else {
+        EXIT_CODE_PASS
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_classify_exit_code() {
+        assert_eq!(classify_exit_code(Some(0)), NpmExitClass::Success);
+        assert_eq!(
+            classify_exit_code(Some(1)),
+            NpmExitClass::VulnerabilitiesFound
+        );
+        assert_eq!(classify_exit_code(Some(2)), NpmExitClass::UsageError);
+        assert_eq!(classify_exit_code(Some(127)), NpmExitClass::Unknown);
+        assert_eq!(classify_exit_code(Some(-1)), NpmExitClass::Unknown);
+        assert_eq!(classify_exit_code(None), NpmExitClass::Unknown);
+    }
+
+    #[test]
+    fn test_exit_code() -> Result<(), crate::Error> {
+        // the worst severity in this report is critical (lodash)
+        let data = NpmAuditData::Version2(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v2.json"
+        ))?);
+        assert_eq!(exit_code(&data, Severity::Low), EXIT_CODE_FAIL);
+        assert_eq!(exit_code(&data, Severity::High), EXIT_CODE_FAIL);
+        assert_eq!(exit_code(&data, Severity::Critical), EXIT_CODE_FAIL);
+        // the worst severity in this report is high (qs, lodash)
+        let data = NpmAuditData::Version1(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v1_cwes.json"
+        ))?);
+        assert_eq!(exit_code(&data, Severity::Moderate), EXIT_CODE_FAIL);
+        assert_eq!(exit_code(&data, Severity::High), EXIT_CODE_FAIL);
+        assert_eq!(exit_code(&data, Severity::Critical), EXIT_CODE_PASS);
+        let data = NpmAuditData::Version2(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v2_clean.json"
+        ))?);
+        assert_eq!(exit_code(&data, Severity::None), EXIT_CODE_PASS);
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+//! This finds the JSON object in the output of npm
+//!
+//! npm or wrapper scripts sometimes print log lines before or after the JSON
+//! output, the scanner here finds the object by matching braces while
+//! keeping track of strings so braces inside strings are ignored
+
+/// The result of scanning program output for a JSON object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonScan<'a> {
+    /// a complete JSON object was found
+    Complete(&'a str),
+    /// a JSON object starts but the output ends before it is closed
+    Truncated,
+    /// there is no JSON object in the output
+    NotFound,
+}
+
+/// scans the output for the first JSON object
+///
+/// the object has to start at the beginning of a line (ignoring whitespace)
+/// so braces in log lines before it are not mistaken for the start of the
+/// object
+pub fn scan_json_object(output: &str) -> JsonScan<'_> {
+    let Some(start) = find_object_start(output) else {
+        return JsonScan::NotFound;
+    };
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in output[start..].char_indices() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return JsonScan::Complete(&output[start..start + i + c.len_utf8()]);
+                }
+            }
+            _ => {}
+        }
+    }
+    JsonScan::Truncated
+}
+
+/// the first complete JSON object in the output, see [scan_json_object]
+pub fn extract_json_object(output: &str) -> Option<&str> {
+    match scan_json_object(output) {
+        JsonScan::Complete(json) => Some(json),
+        JsonScan::Truncated | JsonScan::NotFound => None,
+    }
+}
+
+/// the byte offset of the first { which is the first non-whitespace
+/// character on its line
+fn find_object_start(output: &str) -> Option<usize> {
+    let mut offset = 0;
+    for line in output.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('{') {
+            return Some(offset + line.len() - trimmed.len());
+        }
+        offset += line.len();
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_scan_complete() {
+        assert_eq!(
+            scan_json_object("{\"a\": {\"b\": [1, 2]}}"),
+            JsonScan::Complete("{\"a\": {\"b\": [1, 2]}}")
+        );
+        assert_eq!(
+            scan_json_object("npm WARN config {x}\n  {\"a\": \"}\"}\ntrailing"),
+            JsonScan::Complete("{\"a\": \"}\"}")
+        );
+        assert_eq!(
+            scan_json_object("{\"a\": \"\\\"}\"}"),
+            JsonScan::Complete("{\"a\": \"\\\"}\"}")
+        );
+    }
+
+    #[test]
+    fn test_extract_pretty_and_compact() -> Result<(), crate::Error> {
+        let output = include_str!("../fixtures/npm_audit_v2_pretty_with_noise.txt");
+        let pretty = extract_json_object(output).expect("the report is complete");
+        assert!(pretty.starts_with("{\n  \"auditReportVersion\""));
+        assert!(pretty.ends_with("}\n}"));
+        let value: serde_json::Value = serde_json::from_str(pretty)?;
+        let compact = serde_json::to_string(&value)?;
+        let noisy_compact = format!("npm timing {{\"a\": 1}}\n{}\nnpm notice }}\n", compact);
+        assert_eq!(extract_json_object(&noisy_compact), Some(compact.as_str()));
+        let data = crate::audit::parse_audit_str(pretty, 2)?;
+        assert_eq!(
+            data.iter_vulnerabilities()
+                .find(|v| v.package == "lodash")
+                .map(|v| v.title),
+            Some(
+                "Prototype Pollution via \"{constructor: {prototype}}\" in lodash \\ }".to_string()
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_truncated() {
+        assert_eq!(
+            scan_json_object(include_str!("../fixtures/npm_audit_v2_truncated.json")),
+            JsonScan::Truncated
+        );
+        assert_eq!(
+            scan_json_object("{\"a\": \"unterminated }"),
+            JsonScan::Truncated
+        );
+    }
+
+    #[test]
+    fn test_scan_not_found() {
+        assert_eq!(scan_json_object(""), JsonScan::NotFound);
+        assert_eq!(scan_json_object("npm ERR! {not json}"), JsonScan::NotFound);
+        assert_eq!(extract_json_object("nothing here"), None);
+    }
+}
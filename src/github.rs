@@ -0,0 +1,117 @@
+//! This renders npm-audit reports as GitHub Actions workflow commands
+//!
+//! [workflow commands](https://docs.github.com/en/actions/using-workflows/workflow-commands-for-github-actions)
+
+use crate::audit::{NpmAuditData, Severity};
+use std::io::Write;
+
+/// the annotation level for a severity, high and critical vulnerabilities
+/// are errors, everything else a warning
+fn annotation_level(severity: &Severity) -> &'static str {
+    if *severity >= Severity::High {
+        "error"
+    } else {
+        "warning"
+    }
+}
+
+/// escapes the message of a workflow command
+fn escape_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// escapes a property value of a workflow command
+fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// writes a single annotation
+fn write_annotation(
+    w: &mut impl Write,
+    severity: &Severity,
+    package: &str,
+    title: &str,
+    url: &str,
+) -> std::io::Result<()> {
+    writeln!(
+        w,
+        "::{} title={}::{} ({}) {}",
+        annotation_level(severity),
+        escape_property(package),
+        escape_data(title),
+        severity.as_str(),
+        escape_data(url)
+    )
+}
+
+impl NpmAuditData {
+    /// writes one GitHub Actions annotation per advisory
+    ///
+    /// high and critical advisories are written as `::error`, all others as
+    /// `::warning`. The annotation title is the affected package, the message
+    /// contains the advisory title, severity and URL.
+    ///
+    /// for report version 2 only the entries in via with full details are
+    /// written, the other entries refer to packages with their own entries
+    pub fn write_github_annotations(&self, mut w: impl Write) -> std::io::Result<()> {
+        for vulnerability in self.iter_vulnerabilities() {
+            let package = if vulnerability.package.is_empty() {
+                "unknown"
+            } else {
+                &vulnerability.package
+            };
+            write_annotation(
+                &mut w,
+                &vulnerability.severity,
+                package,
+                &vulnerability.title,
+                &vulnerability.url,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn test_write_github_annotations_v2() -> Result<(), Error> {
+        let data = NpmAuditData::Version2(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v2.json"
+        ))?);
+        let mut out = Vec::new();
+        data.write_github_annotations(&mut out)?;
+        let out = String::from_utf8(out).expect("annotations are UTF-8");
+        assert_eq!(
+            out,
+            "::error title=glob-parent::glob-parent before 5.1.2 vulnerable to Regular Expression Denial of Service in enclosure regex (high) https://github.com/advisories/GHSA-ww39-953v-wcq6\n\
+             ::warning title=lodash::Regular Expression Denial of Service (ReDoS) in lodash (moderate) https://github.com/advisories/GHSA-29mw-wpgm-hmr9\n\
+             ::error title=lodash::Prototype Pollution in lodash (critical) https://github.com/advisories/GHSA-p6mc-m468-83gw\n\
+             ::warning title=minimist::Prototype Pollution in minimist (moderate) https://github.com/advisories/GHSA-vh95-rmgr-6w4m\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_github_annotations_v1() -> Result<(), Error> {
+        let data = NpmAuditData::Version1(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v1.json"
+        ))?);
+        let mut out = Vec::new();
+        data.write_github_annotations(&mut out)?;
+        let out = String::from_utf8(out).expect("annotations are UTF-8");
+        assert_eq!(out.lines().count(), 4);
+        assert!(out.lines().any(|l| l == "::warning title=minimist::Prototype Pollution in minimist (moderate) https://github.com/advisories/GHSA-vh95-rmgr-6w4m"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_escape_property() {
+        assert_eq!(escape_property("a:b,c%\n"), "a%3Ab%2Cc%25%0A");
+    }
+}
@@ -0,0 +1,97 @@
+//! Matching of paths and package names against glob patterns, e.g. those
+//! npm uses for workspaces
+
+/// does the path (with / as separator) match the pattern
+///
+/// `*` matches any characters except /, `?` any single character except /
+/// and a `**` segment any number of path segments
+pub(crate) fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+    let path: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    match_segments(&pattern, &path)
+}
+
+/// matches the segments of the pattern against those of the path
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    wildcard_match(
+        pattern,
+        path,
+        |segment| *segment == "**",
+        |segment, name| match_segment(segment.as_bytes(), name.as_bytes()),
+    )
+}
+
+/// matches a single segment with `*` and `?` wildcards
+fn match_segment(pattern: &[u8], name: &[u8]) -> bool {
+    wildcard_match(pattern, name, |c| *c == b'*', |c, n| *c == b'?' || c == n)
+}
+
+/// matches the text against a pattern in which the elements for which
+/// `is_star` is true match any number of elements of the text and all other
+/// elements match one element for which `matches` is true
+///
+/// this only returns to the last star when a match fails instead of trying
+/// every split for every star, so it takes O(n·m) steps instead of an
+/// exponential number for patterns with many stars
+fn wildcard_match<P, T>(
+    pattern: &[P],
+    text: &[T],
+    is_star: impl Fn(&P) -> bool,
+    matches: impl Fn(&P, &T) -> bool,
+) -> bool {
+    let mut p = 0;
+    let mut t = 0;
+    // the position after the last star in the pattern and the position in
+    // the text it continues matching at if the rest does not match
+    let mut backtrack = None;
+    while t < text.len() {
+        if p < pattern.len() && is_star(&pattern[p]) {
+            p += 1;
+            backtrack = Some((p, t));
+        } else if p < pattern.len() && matches(&pattern[p], &text[t]) {
+            p += 1;
+            t += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            // let the last star match one more element
+            p = star_p;
+            t = star_t + 1;
+            backtrack = Some((star_p, t));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(is_star)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("packages/*", "packages/a"));
+        assert!(!glob_match("packages/*", "packages/a/b"));
+        assert!(glob_match("packages/**", "packages/a/b"));
+        assert!(glob_match("**/b", "packages/a/b"));
+        assert!(glob_match("pkg-?", "pkg-1"));
+        assert!(!glob_match("pkg-?", "pkg-10"));
+        assert!(glob_match("@scope/*-utils", "@scope/date-utils"));
+        assert!(!glob_match("packages/*", "other/a"));
+        assert!(match_segment(b"*", b""));
+        assert!(glob_match("a*b*c", "aXbYbc"));
+        assert!(!glob_match("a*b*c", "aXbYbcd"));
+        assert!(glob_match("packages/**/x", "packages/x"));
+        assert!(glob_match("**/a/**/b", "x/a/y/z/b"));
+        assert!(!glob_match("**/a/**/b", "x/a/y/z/c"));
+    }
+
+    #[test]
+    fn test_glob_match_many_stars() {
+        let name = "a".repeat(64);
+        assert!(!glob_match(&format!("{}b", "a*".repeat(32)), &name));
+        assert!(glob_match(&"a*".repeat(32), &name));
+        let path = vec!["a"; 64].join("/");
+        assert!(!glob_match(&format!("{}b", "**/a/".repeat(16)), &path));
+        assert!(glob_match(&"**/a/".repeat(16), &path));
+    }
+}
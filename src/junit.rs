@@ -0,0 +1,153 @@
+//! This renders npm-audit reports as JUnit XML so CI systems can show them
+//! as test results
+//!
+//! each vulnerable package is a failed test case, the report does not list
+//! the packages without vulnerabilities so a clean report is a single passed
+//! test case
+
+use crate::audit::{NpmAuditData, UnifiedVulnerability};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// the name of the test suite and the class name of the test cases
+const SUITE_NAME: &str = "npm audit";
+
+/// escapes the characters with a special meaning in XML text and attributes
+///
+/// control characters other than tab, LF and CR are not allowed in XML 1.0
+/// at all, they are replaced with U+FFFD
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            '\u{0}'..='\u{1f}' => escaped.push(char::REPLACEMENT_CHARACTER),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl NpmAuditData {
+    /// a JUnit XML test suite with one failed test case per vulnerable
+    /// package
+    ///
+    /// the failure message names the first advisory for the package (or says
+    /// it is only vulnerable through its dependencies), the failure text
+    /// lists all advisories with severity and URL. Packages are in
+    /// alphabetical order so the output is deterministic.
+    pub fn to_junit(&self) -> String {
+        let severities = self.package_severities();
+        let mut advisories: BTreeMap<String, Vec<UnifiedVulnerability>> = BTreeMap::new();
+        for vulnerability in self.iter_vulnerabilities() {
+            advisories
+                .entry(vulnerability.package.to_owned())
+                .or_default()
+                .push(vulnerability);
+        }
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        // writing to a String does not fail
+        let _ = writeln!(
+            xml,
+            "<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">",
+            SUITE_NAME,
+            severities.len().max(1),
+            severities.len()
+        );
+        if severities.is_empty() {
+            let _ = writeln!(
+                xml,
+                "  <testcase classname=\"{}\" name=\"no vulnerabilities\"/>",
+                SUITE_NAME
+            );
+        }
+        for (package, severity) in &severities {
+            let advisories = advisories.get(package).map(Vec::as_slice).unwrap_or(&[]);
+            let message = match advisories.first() {
+                Some(advisory) => format!("{} ({})", advisory.title, advisory.severity.as_str()),
+                None => format!(
+                    "vulnerable through its dependencies ({})",
+                    severity.as_str()
+                ),
+            };
+            let _ = writeln!(
+                xml,
+                "  <testcase classname=\"{}\" name=\"{}\">",
+                SUITE_NAME,
+                escape_xml(package)
+            );
+            let _ = write!(
+                xml,
+                "    <failure type=\"{}\" message=\"{}\">",
+                severity.as_str(),
+                escape_xml(&message)
+            );
+            for advisory in advisories {
+                let _ = write!(
+                    xml,
+                    "\n{} ({}) {}",
+                    escape_xml(&advisory.title),
+                    advisory.severity.as_str(),
+                    escape_xml(&advisory.url)
+                );
+            }
+            let _ = writeln!(xml, "</failure>\n  </testcase>");
+        }
+        xml.push_str("</testsuite>\n");
+        xml
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::audit::parse_audit_str;
+    use crate::Error;
+
+    #[test]
+    fn test_to_junit_is_well_formed() -> Result<(), Error> {
+        let data = parse_audit_str(include_str!("../fixtures/npm_audit_v2.json"), 2)?;
+        let xml = data.to_junit();
+        let document = roxmltree::Document::parse(&xml).expect("well-formed XML");
+        let suite = document.root_element();
+        assert_eq!(suite.tag_name().name(), "testsuite");
+        assert_eq!(suite.attribute("failures"), Some("6"));
+        let names: Vec<&str> = suite
+            .children()
+            .filter(|n| n.has_tag_name("testcase"))
+            .filter_map(|n| n.attribute("name"))
+            .collect();
+        assert_eq!(names.len(), 6);
+        assert!(names.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(xml, data.to_junit());
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_junit_clean_report() -> Result<(), Error> {
+        let data = parse_audit_str(include_str!("../fixtures/npm_audit_v2_clean.json"), 2)?;
+        let xml = data.to_junit();
+        let document = roxmltree::Document::parse(&xml).expect("well-formed XML");
+        assert_eq!(document.root_element().attribute("failures"), Some("0"));
+        assert!(!xml.contains("<failure"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(
+            escape_xml(r#"<a href="x">&'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&apos;&lt;/a&gt;"
+        );
+        assert_eq!(
+            escape_xml("a\u{0}b\u{1b}[31mc\u{1f}\td\ne\rf"),
+            "a\u{fffd}b\u{fffd}[31mc\u{fffd}\td\ne\rf"
+        );
+    }
+}
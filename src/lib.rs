@@ -24,8 +24,27 @@
 #![doc = include_str!("../README.md")]
 
 pub mod audit;
+pub mod correlation;
+#[cfg(feature = "cyclonedx")]
+pub mod cyclonedx;
+pub mod exit_code;
+pub mod extract;
+pub mod github;
+mod glob;
+pub mod junit;
 pub mod outdated;
+pub mod pnpm;
+mod process;
+pub mod range;
+pub mod redact;
+#[cfg(feature = "tarball")]
+mod tarball;
+pub mod trend;
+pub mod version;
+pub mod workspace;
+pub mod yarn;
 
+use std::borrow::Cow;
 use thiserror::Error;
 
 /// Error type for npm_parser
@@ -46,4 +65,114 @@ pub enum Error {
     /// This is likely to be an error when executing the program using std::process
     #[error("I/O Error: {0}")]
     StdIoError(#[from] std::io::Error),
+    /// This means a dependency group was configured to be both omitted and
+    /// included in an npm call
+    #[error("Dependency group {0} can not be both omitted and included")]
+    ConflictingDependencyGroup(crate::audit::DependencyGroup),
+    /// This means the path given as a lockfile does not exist or is not
+    /// named package-lock.json or npm-shrinkwrap.json
+    #[error("Not a package-lock.json or npm-shrinkwrap.json file: {}", .0.display())]
+    InvalidLockfile(std::path::PathBuf),
+    /// This means there is no node_modules directory in the project and
+    /// auditing only the lockfile was not allowed
+    #[error("No node_modules directory at {}, run npm install first", .0.display())]
+    NodeModulesMissing(std::path::PathBuf),
+    /// This means the JSON output of the program ends before the JSON object
+    /// is complete, e.g. because the output was cut off by a size limit
+    #[error("The JSON output of the program is truncated")]
+    TruncatedOutput,
+    /// This means npm was run with --offline but its cache did not contain
+    /// everything needed, the call needs network access
+    #[error("npm needs network access, the cache does not suffice for an offline run")]
+    NetworkRequired,
+    /// This means npm did not output JSON even though --json was passed,
+    /// likely because a setting in an .npmrc overrides it
+    #[error("npm did not honor --json and printed something else, check .npmrc for settings overriding the output format")]
+    NonJsonOutput,
+    /// This means an audit report was supposed to be parsed in a report
+    /// format this crate does not know
+    #[error("Unknown audit report format {0}")]
+    UnknownReportFormat(u8),
+    /// This means the output of the program contained no report at all
+    #[error("The output of the program contains no report")]
+    NoReport,
+    /// This means an npm config key passed to a command is empty or contains
+    /// whitespace or an equals sign
+    #[error("Invalid npm config key {0:?}")]
+    InvalidConfigKey(String),
+    /// This means npm exited with an error and its output could not be
+    /// parsed as a report, stderr usually contains the reason
+    #[error("npm failed with exit code {status:?}: {stderr}")]
+    NpmCommandFailed {
+        /// the exit code of npm, None if it was terminated by a signal
+        status: Option<i32>,
+        /// what npm printed on stderr
+        stderr: String,
+    },
+    /// This means a severity name is not one of those npm uses
+    #[error("Unknown severity {0:?}")]
+    UnknownSeverity(String),
+    /// This means npm --version could not be run or failed, so the npm
+    /// installation is likely broken
+    #[error("Could not determine the npm version: {stderr}")]
+    VersionProbeFailed {
+        /// what npm printed on stderr, or why it could not be started
+        stderr: String,
+    },
+    /// This means npm did not exit before the configured timeout expired and
+    /// was killed
+    #[error("npm did not finish within {0:?}")]
+    Timeout(std::time::Duration),
+}
+
+/// decodes the output of a program as UTF-8
+///
+/// if lossy is true invalid sequences are replaced with the unicode
+/// replacement character instead of returning an error
+pub(crate) fn decode_output(bytes: &[u8], lossy: bool) -> Result<Cow<'_, str>, Error> {
+    if lossy {
+        Ok(String::from_utf8_lossy(bytes))
+    } else {
+        Ok(Cow::Borrowed(std::str::from_utf8(bytes)?))
+    }
+}
+
+/// creates an empty directory for a test in the system temp directory
+#[cfg(test)]
+pub(crate) fn test_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("npm-parser-test-{}-{}", std::process::id(), name));
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).expect("could not remove old test directory");
+    }
+    std::fs::create_dir_all(&dir).expect("could not create test directory");
+    dir
+}
+
+/// writes a shell script to use instead of npm in tests
+#[cfg(all(test, unix))]
+pub(crate) fn fake_npm(dir: &std::path::Path, script: &str) -> std::path::PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+    let path = dir.join("npm");
+    std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).expect("could not write fake npm");
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+        .expect("could not make fake npm executable");
+    path
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode_output_invalid_utf8() {
+        let bytes = b"{\"description\": \"caf\xe9\"}";
+        assert!(matches!(
+            decode_output(bytes, false),
+            Err(Error::Utf8Error(_))
+        ));
+        assert_eq!(
+            decode_output(bytes, true).unwrap(),
+            "{\"description\": \"caf\u{FFFD}\"}"
+        );
+    }
 }
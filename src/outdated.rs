@@ -1,31 +1,71 @@
 //! This parses the output of npm-outdated
+use crate::audit::AuditOptions;
+use crate::exit_code::found_something;
+use crate::glob::glob_match;
 use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::process::Command;
 use std::str::from_utf8;
 use tracing::{debug, warn};
+use versions::SemVer;
 
 /// Outer structure for parsing npm-outdated output
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct NpmOutdatedData(pub BTreeMap<String, PackageStatus>);
 
+impl NpmOutdatedData {
+    /// the packages whose name matches the glob pattern, sorted by name
+    ///
+    /// `*` matches any characters and `?` a single character, both except
+    /// the / between scope and name, so `@babel/*` matches all packages in a
+    /// scope and `*-loader` only unscoped packages
+    pub fn matching(&self, pattern: &str) -> Vec<(&String, &PackageStatus)> {
+        self.0
+            .iter()
+            .filter(|(name, _)| glob_match(pattern, name))
+            .collect()
+    }
+}
+
 /// Inner, per-package structure when parsing npm-outdated output
 ///
 /// Meaning of the fields is from [npm-outdated](https://docs.npmjs.com/cli/v7/commands/npm-outdated)
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct PackageStatus {
+    /// current is the currently-installed version of the package
+    ///
+    /// optional since npm omits it for packages which are not installed
+    #[serde(default)]
+    pub current: Option<String>,
     /// wanted is the maximum version of the package that satisfies the
     /// semver range specified in package.json. If there's no available
     /// semver range (i.e. you're running npm outdated --global, or
     /// the package isn't included in package.json), then wanted shows
     /// the currently-installed version.
-    pub wanted: String,
+    ///
+    /// optional since npm omits it or emits null for git or linked
+    /// dependencies
+    pub wanted: Option<String>,
     /// latest is the version of the package tagged as latest in the registry.
     /// Running npm publish with no special configuration will publish the
     /// package with a dist-tag of latest. This may or may not be the maximum
     /// version of the package, or the most-recently published version of the
     /// package, depending on how the package's developer manages the latest
     /// dist-tag.
-    pub latest: String,
+    ///
+    /// optional since npm omits it or emits null for git or linked
+    /// dependencies
+    pub latest: Option<String>,
+    /// when the latest version was published
+    ///
+    /// only included by some npm versions
+    #[serde(
+        default,
+        rename = "latestPublished",
+        serialize_with = "crate::audit::serialize_optional_rfc3339",
+        deserialize_with = "crate::audit::deserialize_optional_rfc3339"
+    )]
+    pub latest_published: Option<time::OffsetDateTime>,
     /// where in the physical tree the package is located.
     pub location: Option<String>,
     /// shows which package depends on the displayed dependency
@@ -36,15 +76,121 @@ pub struct PackageStatus {
     /// dependency. Packages not included in package.json are always marked
     /// dependencies.
     #[serde(rename = "type")]
-    pub package_type: String,
+    pub package_type: DependencyType,
     /// the homepage value contained in the package's packument
     ///
     /// optional since it is not included in all npm versions
     pub homepage: Option<String>,
 }
 
+impl PackageStatus {
+    /// which part of the version changes when updating from the current to
+    /// the latest version
+    ///
+    /// None if either version is missing, not a plain semver version (e.g. a
+    /// prerelease or a git dependency) or latest is not newer than current
+    pub fn update_kind(&self) -> Option<UpdateKind> {
+        let parse = |v: &Option<String>| {
+            v.as_deref()
+                .and_then(SemVer::new)
+                .filter(|v| v.pre_rel.is_none())
+        };
+        let current = parse(&self.current)?;
+        let latest = parse(&self.latest)?;
+        let current = (current.major, current.minor, current.patch);
+        let latest = (latest.major, latest.minor, latest.patch);
+        if latest <= current {
+            None
+        } else if latest.0 != current.0 {
+            Some(UpdateKind::Major)
+        } else if latest.1 != current.1 {
+            Some(UpdateKind::Minor)
+        } else {
+            Some(UpdateKind::Patch)
+        }
+    }
+}
+
+/// The semver component changed by an update
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum UpdateKind {
+    /// only the patch version changes
+    Patch,
+    /// the minor version changes
+    Minor,
+    /// the major version changes
+    Major,
+}
+
+/// Which section of package.json a dependency is declared in
+///
+/// npm names these after the package.json keys, e.g. devDependencies
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DependencyType {
+    /// dependencies, also used for packages not in package.json
+    Prod,
+    /// devDependencies
+    Dev,
+    /// peerDependencies
+    Peer,
+    /// optionalDependencies
+    Optional,
+    /// any other value npm might emit
+    Other(String),
+}
+
+impl DependencyType {
+    /// the name npm uses for this dependency type
+    pub fn as_str(&self) -> &str {
+        match self {
+            DependencyType::Prod => "dependencies",
+            DependencyType::Dev => "devDependencies",
+            DependencyType::Peer => "peerDependencies",
+            DependencyType::Optional => "optionalDependencies",
+            DependencyType::Other(other) => other,
+        }
+    }
+}
+
+impl From<&str> for DependencyType {
+    fn from(s: &str) -> Self {
+        match s {
+            "dependencies" => DependencyType::Prod,
+            "devDependencies" => DependencyType::Dev,
+            "peerDependencies" => DependencyType::Peer,
+            "optionalDependencies" => DependencyType::Optional,
+            other => DependencyType::Other(other.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for DependencyType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl serde::Serialize for DependencyType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for DependencyType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = <std::borrow::Cow<'de, str> as serde::Deserialize>::deserialize(deserializer)?;
+        Ok(DependencyType::from(s.as_ref()))
+    }
+}
+
 /// What the exit code indicated about required updates
-#[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum IndicatedUpdateRequirement {
     /// No update is required
     UpToDate,
@@ -65,37 +211,205 @@ impl std::fmt::Display for IndicatedUpdateRequirement {
     }
 }
 
-/// main entry point for the npm-oudated call
-pub fn outdated() -> Result<(IndicatedUpdateRequirement, NpmOutdatedData), crate::Error> {
-    let mut cmd = Command::new("npm");
+/// Builder for a call to npm-outdated
+///
+/// [outdated] is a shortcut for running this with the default settings
+#[derive(Debug, Default, Clone)]
+pub struct OutdatedCommand {
+    /// the npm binary and working directory
+    options: AuditOptions,
+    /// pass --legacy-peer-deps to npm
+    legacy_peer_deps: bool,
+    /// the npm cache directory passed as `--cache <path>`
+    cache_dir: Option<PathBuf>,
+}
+
+impl OutdatedCommand {
+    /// creates a builder with the default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-    cmd.args(["outdated", "--json", "--long"]);
+    /// use the npm binary and working directory from the options
+    pub fn options(mut self, options: AuditOptions) -> Self {
+        self.options = options;
+        self
+    }
 
-    let output = cmd.output()?;
+    /// pass --legacy-peer-deps to npm
+    ///
+    /// this makes npm ignore peer dependencies when building the dependency
+    /// tree, see [crate::audit::AuditCommand::legacy_peer_deps]
+    pub fn legacy_peer_deps(mut self, legacy_peer_deps: bool) -> Self {
+        self.legacy_peer_deps = legacy_peer_deps;
+        self
+    }
 
+    /// use the given directory as the npm cache (`--cache <path>`)
+    ///
+    /// see [crate::audit::AuditCommand::cache_dir]
+    pub fn cache_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(path.into());
+        self
+    }
+
+    /// the arguments passed to npm for the outdated call
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec![
+            "outdated".to_string(),
+            "--json".to_string(),
+            "--long".to_string(),
+        ];
+        if self.legacy_peer_deps {
+            args.push("--legacy-peer-deps".to_string());
+        }
+        if let Some(cache_dir) = &self.cache_dir {
+            args.push("--cache".to_string());
+            args.push(cache_dir.to_string_lossy().into_owned());
+        }
+        args
+    }
+
+    /// runs npm-outdated with the configured settings
+    pub fn run(&self) -> Result<(IndicatedUpdateRequirement, NpmOutdatedData), crate::Error> {
+        let mut cmd = self.command();
+        let output = crate::process::output(&mut cmd, None, self.options.timeout)?;
+        result_from_output(output)
+    }
+
+    /// like [OutdatedCommand::run] but without blocking the async executor
+    /// while npm runs
+    ///
+    /// if the returned future is dropped before it finishes npm is killed.
+    /// This requires the tokio feature and has to be called within a tokio
+    /// runtime.
+    #[cfg(feature = "tokio")]
+    pub async fn run_async(
+        &self,
+    ) -> Result<(IndicatedUpdateRequirement, NpmOutdatedData), crate::Error> {
+        let output =
+            crate::process::output_async(self.command(), None, self.options.timeout).await?;
+        result_from_output(output)
+    }
+
+    /// the npm command for the outdated call
+    fn command(&self) -> Command {
+        let mut cmd = Command::new(self.options.npm());
+
+        cmd.args(self.args());
+        if let Some(dir) = &self.options.working_dir {
+            cmd.current_dir(dir);
+        }
+        cmd
+    }
+}
+
+/// interprets the output of the npm outdated call
+fn result_from_output(
+    output: std::process::Output,
+) -> Result<(IndicatedUpdateRequirement, NpmOutdatedData), crate::Error> {
     if !output.status.success() {
         warn!(
             "npm outdated did not return with a successful exit code: {}",
             output.status
         );
-        debug!("stdout:\n{}", from_utf8(&output.stdout)?);
+        debug!("stdout:\n{}", String::from_utf8_lossy(&output.stdout));
         if !output.stderr.is_empty() {
-            warn!("stderr:\n{}", from_utf8(&output.stderr)?);
+            warn!("stderr:\n{}", String::from_utf8_lossy(&output.stderr));
         }
     }
 
-    let update_requirement = if output.status.success() {
-        IndicatedUpdateRequirement::UpToDate
-    } else {
+    let update_requirement = if found_something(&output)? {
         IndicatedUpdateRequirement::UpdateRequired
+    } else {
+        IndicatedUpdateRequirement::UpToDate
     };
 
-    let json_str = from_utf8(&output.stdout)?;
-    let jd = &mut serde_json::Deserializer::from_str(json_str);
-    let data: NpmOutdatedData = serde_path_to_error::deserialize(jd)?;
+    let data = parse_outdated(&output.stdout)?;
     Ok((update_requirement, data))
 }
 
+/// parses the output of npm outdated --json
+///
+/// returns [crate::Error::NonJsonOutput] if the output is not a JSON object
+/// at all, e.g. the table npm prints if an .npmrc overrides --json. This is
+/// checked before the output is decoded so a table with package names which
+/// are not valid UTF-8 is reported the same way.
+fn parse_outdated(json: &[u8]) -> Result<NpmOutdatedData, crate::Error> {
+    if !matches!(
+        json.iter().find(|b| !b.is_ascii_whitespace()),
+        None | Some(b'{')
+    ) {
+        return Err(crate::Error::NonJsonOutput);
+    }
+    let jd = &mut serde_json::Deserializer::from_str(from_utf8(json)?);
+    let data: NpmOutdatedData = serde_path_to_error::deserialize(jd)?;
+    Ok(data)
+}
+
+/// the visitor used by [outdated_stream]
+struct StreamVisitor<F> {
+    /// called for each package
+    callback: F,
+}
+
+impl<'de, F> serde::de::Visitor<'de> for StreamVisitor<F>
+where
+    F: FnMut(&str, &PackageStatus),
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a map of package names to package status")
+    }
+
+    fn visit_map<A>(mut self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        while let Some((name, status)) = map.next_entry::<String, PackageStatus>()? {
+            (self.callback)(&name, &status);
+        }
+        Ok(())
+    }
+}
+
+/// parses npm-outdated output package by package, calling the callback for
+/// each one instead of collecting them into an [NpmOutdatedData]
+///
+/// only one package is held in memory at a time, the callback is called in
+/// the order the packages appear in the JSON, not sorted by name
+pub fn outdated_stream(
+    reader: impl std::io::Read,
+    callback: impl FnMut(&str, &PackageStatus),
+) -> Result<(), crate::Error> {
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    serde::Deserializer::deserialize_map(&mut deserializer, StreamVisitor { callback })?;
+    deserializer.end()?;
+    Ok(())
+}
+
+/// main entry point for the npm-oudated call
+pub fn outdated() -> Result<(IndicatedUpdateRequirement, NpmOutdatedData), crate::Error> {
+    OutdatedCommand::new().run()
+}
+
+/// like [outdated] but with the npm binary and working directory from the
+/// options
+pub fn outdated_with_options(
+    options: &AuditOptions,
+) -> Result<(IndicatedUpdateRequirement, NpmOutdatedData), crate::Error> {
+    OutdatedCommand::new().options(options.clone()).run()
+}
+
+/// like [outdated] but without blocking the async executor while npm runs,
+/// see [OutdatedCommand::run_async]
+#[cfg(feature = "tokio")]
+pub async fn outdated_async() -> Result<(IndicatedUpdateRequirement, NpmOutdatedData), crate::Error>
+{
+    OutdatedCommand::new().run_async().await
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -108,4 +422,250 @@ mod test {
         outdated()?;
         Ok(())
     }
+
+    #[test]
+    fn test_clone_for_threads() -> Result<(), Error> {
+        let data: NpmOutdatedData =
+            serde_json::from_str(include_str!("../fixtures/npm_outdated_git_dependency.json"))?;
+        let copy = data.clone();
+        let names = std::thread::spawn(move || copy.0.keys().cloned().collect::<Vec<_>>())
+            .join()
+            .expect("thread panicked");
+        assert_eq!(names, data.0.keys().cloned().collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dependency_type() -> Result<(), Error> {
+        for (npm, dependency_type) in [
+            ("dependencies", DependencyType::Prod),
+            ("devDependencies", DependencyType::Dev),
+            ("peerDependencies", DependencyType::Peer),
+            ("optionalDependencies", DependencyType::Optional),
+            (
+                "bundleDependencies",
+                DependencyType::Other("bundleDependencies".to_string()),
+            ),
+        ] {
+            let json = serde_json::Value::from(npm);
+            assert_eq!(
+                serde_json::from_value::<DependencyType>(json.clone())?,
+                dependency_type
+            );
+            assert_eq!(serde_json::to_value(&dependency_type)?, json);
+        }
+        let data: NpmOutdatedData =
+            serde_json::from_str(include_str!("../fixtures/npm_outdated_git_dependency.json"))?;
+        assert_eq!(data.0["lodash"].package_type, DependencyType::Prod);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_git_dependency_without_latest() -> Result<(), Error> {
+        let data: NpmOutdatedData =
+            serde_json::from_str(include_str!("../fixtures/npm_outdated_git_dependency.json"))?;
+        let lodash = &data.0["lodash"];
+        assert_eq!(lodash.wanted.as_deref(), Some("4.17.21"));
+        assert_eq!(lodash.latest.as_deref(), Some("4.17.21"));
+        let fork = &data.0["my-fork"];
+        assert_eq!(fork.wanted, None);
+        assert_eq!(fork.latest, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_long_output_with_current() -> Result<(), Error> {
+        let data = parse_outdated(include_bytes!("../fixtures/npm_outdated_long.json"))?;
+        assert_eq!(data.0["lodash"].current.as_deref(), Some("4.17.20"));
+        let typescript = &data.0["typescript"];
+        assert_eq!(typescript.current.as_deref(), Some("4.9.5"));
+        assert_eq!(typescript.package_type, DependencyType::Dev);
+        assert_eq!(typescript.update_kind(), Some(UpdateKind::Major));
+        let left_pad = &data.0["left-pad"];
+        assert_eq!(left_pad.current, None);
+        assert_eq!(left_pad.update_kind(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_kind() -> Result<(), Error> {
+        let data: NpmOutdatedData = serde_json::from_str(include_str!(
+            "../fixtures/npm_outdated_latest_published.json"
+        ))?;
+        assert_eq!(data.0["lodash"].current.as_deref(), Some("4.17.20"));
+        assert_eq!(data.0["lodash"].update_kind(), Some(UpdateKind::Patch));
+        assert_eq!(data.0["zod"].update_kind(), Some(UpdateKind::Minor));
+        let mut status = data.0["zod"].clone();
+        status.latest = Some("4.0.0".to_string());
+        assert_eq!(status.update_kind(), Some(UpdateKind::Major));
+        status.latest = Some("4.0.0-beta.1".to_string());
+        assert_eq!(status.update_kind(), None);
+        status.latest = Some("3.0.0".to_string());
+        assert_eq!(status.update_kind(), None);
+        let data: NpmOutdatedData =
+            serde_json::from_str(include_str!("../fixtures/npm_outdated_git_dependency.json"))?;
+        assert_eq!(data.0["my-fork"].update_kind(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_outdated_table_output() {
+        assert!(matches!(
+            parse_outdated(include_bytes!("../fixtures/npm_outdated_table.txt")),
+            Err(Error::NonJsonOutput)
+        ));
+        assert!(matches!(
+            parse_outdated(b"Package  Current\ncaf\xe9    1.0.0\n"),
+            Err(Error::NonJsonOutput)
+        ));
+        assert!(parse_outdated(include_bytes!(
+            "../fixtures/npm_outdated_git_dependency.json"
+        ))
+        .is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_outdated_with_options() -> Result<(), Error> {
+        let dir = crate::test_dir("outdated-with-options");
+        let npm = crate::fake_npm(&dir, "cat outdated.json; exit 1");
+        std::fs::write(
+            dir.join("outdated.json"),
+            include_str!("../fixtures/npm_outdated_git_dependency.json"),
+        )?;
+        let (requirement, data) = outdated_with_options(&AuditOptions {
+            npm_path: Some(npm),
+            working_dir: Some(dir),
+            timeout: None,
+            omit: Vec::new(),
+        })?;
+        assert_eq!(requirement, IndicatedUpdateRequirement::UpdateRequired);
+        assert_eq!(data.0.len(), 2);
+        Ok(())
+    }
+
+    #[cfg(all(unix, feature = "tokio"))]
+    #[tokio::test]
+    async fn test_outdated_run_async() -> Result<(), Error> {
+        let dir = crate::test_dir("outdated-run-async");
+        let npm = crate::fake_npm(&dir, "cat outdated.json; exit 1");
+        std::fs::write(
+            dir.join("outdated.json"),
+            include_str!("../fixtures/npm_outdated_git_dependency.json"),
+        )?;
+        let (requirement, data) = OutdatedCommand::new()
+            .options(AuditOptions {
+                npm_path: Some(npm),
+                working_dir: Some(dir),
+                timeout: None,
+                omit: Vec::new(),
+            })
+            .run_async()
+            .await?;
+        assert_eq!(requirement, IndicatedUpdateRequirement::UpdateRequired);
+        assert_eq!(data.0.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_matching() -> Result<(), Error> {
+        let json = r#"{
+            "@babel/core": {"current": "7.0.0", "wanted": "7.0.1", "latest": "7.24.0", "type": "dependencies"},
+            "@babel/preset-env": {"current": "7.0.0", "wanted": "7.0.1", "latest": "7.24.0", "type": "devDependencies"},
+            "@babel-fork/core": {"current": "1.0.0", "wanted": "1.0.0", "latest": "1.1.0", "type": "dependencies"},
+            "css-loader": {"current": "5.0.0", "wanted": "5.2.7", "latest": "7.1.1", "type": "devDependencies"},
+            "style-loader": {"current": "2.0.0", "wanted": "2.0.0", "latest": "4.0.0", "type": "devDependencies"},
+            "loader-utils": {"current": "2.0.0", "wanted": "2.0.4", "latest": "3.2.1", "type": "dependencies"}
+        }"#;
+        let data = parse_outdated(json.as_bytes())?;
+        let names = |pattern: &str| -> Vec<String> {
+            data.matching(pattern)
+                .into_iter()
+                .map(|(name, _)| name.clone())
+                .collect()
+        };
+        assert_eq!(names("@babel/*"), vec!["@babel/core", "@babel/preset-env"]);
+        assert_eq!(names("*-loader"), vec!["css-loader", "style-loader"]);
+        assert_eq!(
+            names("@babel*/core"),
+            vec!["@babel-fork/core", "@babel/core"]
+        );
+        assert_eq!(names("loader-utils"), vec!["loader-utils"]);
+        assert!(names("@types/*").is_empty());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_outdated_failed_with_stderr() {
+        let dir = crate::test_dir("outdated-failed-with-stderr");
+        let npm = crate::fake_npm(&dir, r#"echo "npm ERR! code E404" >&2; exit 3"#);
+        let result = outdated_with_options(&AuditOptions {
+            npm_path: Some(npm),
+            working_dir: Some(dir),
+            timeout: None,
+            omit: Vec::new(),
+        });
+        match result {
+            Err(Error::NpmCommandFailed { status, stderr }) => {
+                assert_eq!(status, Some(3));
+                assert_eq!(stderr, "npm ERR! code E404\n");
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_outdated_stream() -> Result<(), Error> {
+        let json = r#"{
+            "zod": {"current": "3.0.0", "wanted": "3.0.1", "latest": "3.22.4", "location": "node_modules/zod", "type": "dependencies"},
+            "lodash": {"current": "4.17.20", "wanted": "4.17.21", "latest": "4.17.21", "location": "node_modules/lodash", "type": "dependencies"}
+        }"#;
+        let mut seen = Vec::new();
+        outdated_stream(json.as_bytes(), |name, status| {
+            seen.push((name.to_string(), status.latest.clone()));
+        })?;
+        assert_eq!(
+            seen,
+            vec![
+                ("zod".to_string(), Some("3.22.4".to_string())),
+                ("lodash".to_string(), Some("4.17.21".to_string())),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_latest_published() -> Result<(), Error> {
+        let data: NpmOutdatedData = serde_json::from_str(include_str!(
+            "../fixtures/npm_outdated_latest_published.json"
+        ))?;
+        let published = time::OffsetDateTime::parse(
+            "2021-02-20T15:42:16.891Z",
+            &time::format_description::well_known::Rfc3339,
+        )
+        .expect("valid timestamp");
+        assert_eq!(data.0["lodash"].latest_published, Some(published));
+        assert_eq!(data.0["zod"].latest_published, None);
+        let data: NpmOutdatedData =
+            serde_json::from_str(include_str!("../fixtures/npm_outdated_git_dependency.json"))?;
+        assert_eq!(data.0["lodash"].latest_published, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_outdated_command_args() {
+        assert_eq!(
+            OutdatedCommand::new().args(),
+            vec!["outdated", "--json", "--long"]
+        );
+        assert_eq!(
+            OutdatedCommand::new().legacy_peer_deps(true).args(),
+            vec!["outdated", "--json", "--long", "--legacy-peer-deps"]
+        );
+        assert_eq!(
+            OutdatedCommand::new().cache_dir("/tmp/npm-cache").args(),
+            vec!["outdated", "--json", "--long", "--cache", "/tmp/npm-cache"]
+        );
+    }
 }
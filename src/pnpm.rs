@@ -0,0 +1,124 @@
+//! This parses the output of pnpm audit --json
+//!
+//! pnpm uses the layout of npm audit report version 1 (actions, advisories,
+//! muted and metadata) so the output is parsed into [NpmAuditDataV1] after
+//! smoothing over the differences:
+//!
+//! * dependency paths are separated by ` > ` instead of `>` and start with
+//!   `.` for the project itself, the `.` is removed
+//! * cwe is a single string instead of a list
+//! * the updated and deleted fields of advisories may be missing
+//! * metadata.vulnerabilities may not contain a count for every severity,
+//!   missing counts are 0
+
+use crate::audit::NpmAuditDataV1;
+use serde_json::{Map, Value};
+
+/// parses the JSON output of pnpm audit
+pub fn parse_pnpm_audit(json: &str) -> Result<NpmAuditDataV1, crate::Error> {
+    let jd = &mut serde_json::Deserializer::from_str(json);
+    let mut value: Value = serde_path_to_error::deserialize(jd)?;
+    normalize(&mut value);
+    Ok(serde_path_to_error::deserialize(value)?)
+}
+
+/// rewrites the pnpm report into the form of npm audit report version 1
+fn normalize(value: &mut Value) {
+    if let Some(advisories) = value.get_mut("advisories").and_then(Value::as_object_mut) {
+        for advisory in advisories.values_mut().filter_map(Value::as_object_mut) {
+            normalize_advisory(advisory);
+        }
+    }
+    if let Some(actions) = value.get_mut("actions").and_then(Value::as_array_mut) {
+        for resolve in actions
+            .iter_mut()
+            .filter_map(|a| a.get_mut("resolves").and_then(Value::as_array_mut))
+            .flatten()
+        {
+            if let Some(path) = resolve.get_mut("path") {
+                normalize_path(path);
+            }
+        }
+    }
+    if let Some(counts) = value
+        .pointer_mut("/metadata/vulnerabilities")
+        .and_then(Value::as_object_mut)
+    {
+        for severity in ["info", "low", "moderate", "high", "critical"] {
+            counts.entry(severity).or_insert(Value::from(0));
+        }
+    }
+}
+
+/// rewrites a single advisory
+fn normalize_advisory(advisory: &mut Map<String, Value>) {
+    if let Some(findings) = advisory.get_mut("findings").and_then(Value::as_array_mut) {
+        for path in findings
+            .iter_mut()
+            .filter_map(|f| f.get_mut("paths").and_then(Value::as_array_mut))
+            .flatten()
+        {
+            normalize_path(path);
+        }
+    }
+    if let Some(cwe) = advisory.get_mut("cwe") {
+        if cwe.is_string() {
+            *cwe = Value::Array(vec![cwe.take()]);
+        }
+    }
+    for field in ["updated", "deleted"] {
+        advisory.entry(field).or_insert(Value::Null);
+    }
+}
+
+/// turns a pnpm dependency path like `. > mkdirp > minimist` into the npm
+/// form `mkdirp>minimist`
+fn normalize_path(path: &mut Value) {
+    if let Value::String(s) = path {
+        let parts: Vec<&str> = s.split('>').map(str::trim).collect();
+        let parts = match parts.split_first() {
+            Some((&".", rest)) => rest,
+            _ => &parts[..],
+        };
+        *s = parts.join(">");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::audit::Severity;
+    use crate::Error;
+
+    #[test]
+    fn test_parse_pnpm_audit() -> Result<(), Error> {
+        let data = parse_pnpm_audit(include_str!("../fixtures/pnpm_audit.json"))?;
+        let lodash = &data.advisories["1070355"];
+        assert_eq!(lodash.severity, Severity::High);
+        assert_eq!(
+            lodash.findings[0].paths,
+            vec![vec!["lodash"], vec!["webpack-bundle-analyzer", "lodash"]]
+        );
+        assert_eq!(lodash.found_by.as_deref(), Some("Anonymous"));
+        assert_eq!(lodash.cwe_numbers(), vec![77]);
+        assert_eq!(data.metadata.vulnerabilities.info, 0);
+        assert_eq!(data.metadata.total_dependencies, 118);
+        assert_eq!(data.packages_affected_by(1067342), vec!["minimist"]);
+        assert!(data.is_fixed_by_actions(1070355));
+        assert!(!data.is_fixed_by_actions(1067342));
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        for (pnpm, npm) in [
+            (". > mkdirp > minimist", "mkdirp>minimist"),
+            ("packages/a > lodash", "packages/a>lodash"),
+            ("mkdirp>minimist", "mkdirp>minimist"),
+        ] {
+            let mut path = Value::from(pnpm);
+            normalize_path(&mut path);
+            assert_eq!(path, Value::from(npm));
+        }
+    }
+}
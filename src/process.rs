@@ -0,0 +1,261 @@
+//! Running npm with an optional timeout and input
+
+use std::io::{Read, Write};
+use std::process::{Child, Command, Output, Stdio};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// how often to check whether a process with a timeout has exited
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// runs the command and collects its output like [Command::output]
+///
+/// if input is given it is written to the stdin of the process, otherwise
+/// stdin is closed. Input, stdout and stderr are each handled on their own
+/// thread so a process which writes a lot of output before reading all of
+/// its input can not deadlock with us.
+///
+/// if a timeout is given and the process has not exited when it expires the
+/// process is killed and [crate::Error::Timeout] is returned. On unix the
+/// process is started in its own process group and the whole group is killed
+/// so processes started by npm do not outlive it.
+pub(crate) fn output(
+    cmd: &mut Command,
+    input: Option<&[u8]>,
+    timeout: Option<Duration>,
+) -> Result<Output, crate::Error> {
+    if input.is_none() && timeout.is_none() {
+        return Ok(cmd.output()?);
+    }
+    #[cfg(unix)]
+    if timeout.is_some() {
+        std::os::unix::process::CommandExt::process_group(cmd, 0);
+    }
+    let mut child = cmd
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let writer = write_in_background(child.stdin.take(), input);
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+    let status = match timeout {
+        None => child.wait()?,
+        Some(timeout) => {
+            let deadline = Instant::now() + timeout;
+            loop {
+                if let Some(status) = child.try_wait()? {
+                    break status;
+                }
+                if Instant::now() >= deadline {
+                    kill(&mut child);
+                    return Err(crate::Error::Timeout(timeout));
+                }
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        }
+    };
+    if let Some(writer) = writer {
+        match writer.join() {
+            Ok(Err(e)) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(e.into()),
+            Ok(_) => {}
+            Err(_) => {
+                return Err(std::io::Error::other("writing the process input panicked").into())
+            }
+        }
+    }
+    Ok(Output {
+        status,
+        stdout: join_reader(stdout)?,
+        stderr: join_reader(stderr)?,
+    })
+}
+
+/// writes the input to the pipe on a separate thread and closes it
+///
+/// a process exiting without reading all of its input shows up as a broken
+/// pipe error which the caller should ignore
+fn write_in_background<W: Write + Send + 'static>(
+    pipe: Option<W>,
+    input: Option<&[u8]>,
+) -> Option<JoinHandle<std::io::Result<()>>> {
+    let input = input?.to_vec();
+    pipe.map(|mut pipe| std::thread::spawn(move || pipe.write_all(&input)))
+}
+
+/// reads the pipe to the end on a separate thread so the process does not
+/// block on a full pipe while we wait for it
+fn read_in_background<R: Read + Send + 'static>(
+    pipe: Option<R>,
+) -> Option<JoinHandle<std::io::Result<Vec<u8>>>> {
+    pipe.map(|mut pipe| {
+        std::thread::spawn(move || {
+            let mut buffer = Vec::new();
+            pipe.read_to_end(&mut buffer)?;
+            Ok(buffer)
+        })
+    })
+}
+
+/// the data read by [read_in_background]
+fn join_reader(
+    handle: Option<JoinHandle<std::io::Result<Vec<u8>>>>,
+) -> Result<Vec<u8>, crate::Error> {
+    match handle {
+        Some(handle) => Ok(handle
+            .join()
+            .map_err(|_| std::io::Error::other("reading the process output panicked"))??),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// kills the process and on unix its process group and waits for it so it
+/// does not linger as a zombie
+///
+/// errors are ignored since the process may have exited in the meantime
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    kill_group(child.id());
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// kills the process group of the given process, which has to be started
+/// with `process_group(0)`
+///
+/// errors are ignored like in [kill]
+#[cfg(unix)]
+fn kill_group(pid: u32) {
+    if let Some(pid) = i32::try_from(pid)
+        .ok()
+        .and_then(rustix::process::Pid::from_raw)
+    {
+        let _ = rustix::process::kill_process_group(pid, rustix::process::Signal::KILL);
+    }
+}
+
+/// like [output] but waits for the process without blocking the async
+/// executor
+///
+/// the process is killed when the returned future is dropped before it
+/// finishes
+#[cfg(feature = "tokio")]
+pub(crate) async fn output_async(
+    cmd: Command,
+    input: Option<&[u8]>,
+    timeout: Option<Duration>,
+) -> Result<Output, crate::Error> {
+    use tokio::io::AsyncWriteExt as _;
+
+    let mut cmd = tokio::process::Command::from(cmd);
+    #[cfg(unix)]
+    if timeout.is_some() {
+        cmd.process_group(0);
+    }
+    let mut child = cmd
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let pid = child.id();
+    let stdin = child.stdin.take();
+    let write = async move {
+        match (stdin, input) {
+            (Some(mut stdin), Some(input)) => match stdin.write_all(input).await {
+                Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => Err(e),
+                _ => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    };
+    let run = async move {
+        let (written, output) = futures::join!(write, child.wait_with_output());
+        written?;
+        Ok(output?)
+    };
+    let Some(timeout) = timeout else {
+        return run.await;
+    };
+    match tokio::time::timeout(timeout, run).await {
+        Ok(output) => output,
+        Err(_) => {
+            #[cfg(unix)]
+            if let Some(pid) = pid {
+                kill_group(pid);
+            }
+            #[cfg(not(unix))]
+            let _ = pid;
+            Err(crate::Error::Timeout(timeout))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Error;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_with_timeout_finishes() -> Result<(), Error> {
+        let output = output(
+            Command::new("sh").args(["-c", "echo out; echo err >&2"]),
+            None,
+            Some(Duration::from_secs(10)),
+        )?;
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_with_input() -> Result<(), Error> {
+        let input = vec![b'x'; 1024 * 1024];
+        let output = output(Command::new("cat").arg("-"), Some(&input), None)?;
+        assert!(output.status.success());
+        assert_eq!(output.stdout, input);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_with_timeout_expires() {
+        let start = Instant::now();
+        let result = output(
+            Command::new("sh").args(["-c", "sleep 30 & wait"]),
+            None,
+            Some(Duration::from_millis(200)),
+        );
+        assert!(matches!(result, Err(Error::Timeout(_))));
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[cfg(all(unix, feature = "tokio"))]
+    #[tokio::test]
+    async fn test_output_async() -> Result<(), Error> {
+        let input = vec![b'x'; 1024 * 1024];
+        let mut cmd = Command::new("cat");
+        cmd.arg("-");
+        let output = output_async(cmd, Some(&input), Some(Duration::from_secs(10))).await?;
+        assert!(output.status.success());
+        assert_eq!(output.stdout, input);
+        let start = Instant::now();
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "sleep 30 & wait"]);
+        let result = output_async(cmd, None, Some(Duration::from_millis(200))).await;
+        assert!(matches!(result, Err(Error::Timeout(_))));
+        assert!(start.elapsed() < Duration::from_secs(10));
+        Ok(())
+    }
+}
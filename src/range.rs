@@ -0,0 +1,416 @@
+//! This parses npm semver ranges
+//!
+//! [npm semver ranges](https://github.com/npm/node-semver#ranges) as used in
+//! package.json and in the range fields of npm-audit reports
+
+use versions::SemVer;
+
+/// A comparison operator in a single comparator of a range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    /// the version has to be exactly equal
+    Exact,
+    /// the version has to be greater
+    Greater,
+    /// the version has to be greater or equal
+    GreaterEq,
+    /// the version has to be less
+    Less,
+    /// the version has to be less or equal
+    LessEq,
+}
+
+/// A single comparator like `>=1.2.3`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Comparator {
+    /// how to compare
+    op: Op,
+    /// what to compare with
+    version: SemVer,
+}
+
+impl Comparator {
+    /// does the version satisfy this comparator
+    fn matches(&self, version: &SemVer) -> bool {
+        match self.op {
+            Op::Exact => *version == self.version,
+            Op::Greater => *version > self.version,
+            Op::GreaterEq => *version >= self.version,
+            Op::Less => *version < self.version,
+            Op::LessEq => *version <= self.version,
+        }
+    }
+}
+
+/// A possibly incomplete version like `1`, `1.2`, `1.x` or `1.2.3-beta.1`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Partial {
+    /// the major version, None for a wildcard
+    major: Option<u32>,
+    /// the minor version, None for a wildcard or if it was missing
+    minor: Option<u32>,
+    /// the patch version, None for a wildcard or if it was missing
+    patch: Option<u32>,
+    /// the pre-release part (without the leading -), only allowed on complete
+    /// versions
+    pre_release: Option<String>,
+}
+
+impl Partial {
+    /// parses a possibly incomplete version, build metadata is ignored
+    fn parse(s: &str) -> Option<Partial> {
+        let s = s.trim();
+        let s = s.strip_prefix('=').unwrap_or(s);
+        let s = s.strip_prefix('v').unwrap_or(s);
+        let s = s.split_once('+').map_or(s, |(v, _meta)| v);
+        let (numbers, pre_release) = match s.split_once('-') {
+            Some((numbers, pre)) if !pre.is_empty() => (numbers, Some(pre.to_string())),
+            Some(_) => return None,
+            None => (s, None),
+        };
+        let mut parts = numbers.split('.');
+        let mut component = || -> Option<Option<u32>> {
+            match parts.next() {
+                None => Some(None),
+                Some("x") | Some("X") | Some("*") => Some(None),
+                Some(n) => n.parse().ok().map(Some),
+            }
+        };
+        let major = component()?;
+        let minor = component()?;
+        let patch = component()?;
+        if parts.next().is_some() {
+            return None;
+        }
+        // after a wildcard or missing component everything else has to be a
+        // wildcard too
+        if (major.is_none() && (minor.is_some() || patch.is_some()))
+            || (minor.is_none() && patch.is_some())
+        {
+            return None;
+        }
+        if pre_release.is_some() && patch.is_none() {
+            return None;
+        }
+        Some(Partial {
+            major,
+            minor,
+            patch,
+            pre_release,
+        })
+    }
+
+    /// the lowest version matching this partial version
+    fn lower(&self) -> Option<SemVer> {
+        semver(
+            self.major.unwrap_or(0),
+            self.minor.unwrap_or(0),
+            self.patch.unwrap_or(0),
+            self.pre_release.as_deref(),
+        )
+    }
+
+    /// the exclusive upper bound of versions matching this partial version,
+    /// None if it is complete, has no upper bound at all or the bound does not
+    /// fit into the version components
+    fn upper_exclusive(&self) -> Option<SemVer> {
+        match (self.major, self.minor, self.patch) {
+            (Some(major), None, _) => semver(major.checked_add(1)?, 0, 0, Some("0")),
+            (Some(major), Some(minor), None) => semver(major, minor.checked_add(1)?, 0, Some("0")),
+            _ => None,
+        }
+    }
+
+    /// is every component present
+    fn is_complete(&self) -> bool {
+        self.patch.is_some()
+    }
+}
+
+/// constructs a semver version
+fn semver(major: u32, minor: u32, patch: u32, pre_release: Option<&str>) -> Option<SemVer> {
+    match pre_release {
+        Some(pre) => SemVer::new(format!("{}.{}.{}-{}", major, minor, patch, pre)),
+        None => SemVer::new(format!("{}.{}.{}", major, minor, patch)),
+    }
+}
+
+/// constructs a comparator
+fn comparator(op: Op, version: Option<SemVer>) -> Option<Comparator> {
+    Some(Comparator {
+        op,
+        version: version?,
+    })
+}
+
+/// A npm semver range like `^1.2.3`, `>=2.0.0 <2.1.3 || >=3.0.0` or
+/// `1.0.0 - 2.1.8`
+///
+/// pre-release versions are compared by plain semver precedence, npm's rule
+/// that pre-releases only satisfy comparators on the same major.minor.patch
+/// is not applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionRange {
+    /// the alternatives separated by `||`, each a list of comparators which
+    /// all have to match
+    alternatives: Vec<Vec<Comparator>>,
+}
+
+impl VersionRange {
+    /// parses a range, returns None if it is not a valid npm range or if an
+    /// upper bound implied by it (e.g. 2.0.0 for `^1.2.3`) does not fit into
+    /// 32 bit version components
+    pub fn parse(s: &str) -> Option<VersionRange> {
+        let alternatives = s
+            .split("||")
+            .map(parse_comparator_set)
+            .collect::<Option<Vec<_>>>()?;
+        Some(VersionRange { alternatives })
+    }
+
+    /// does the version satisfy this range
+    pub fn matches(&self, version: &SemVer) -> bool {
+        self.alternatives
+            .iter()
+            .any(|set| set.iter().all(|c| c.matches(version)))
+    }
+
+    /// does the version satisfy this range, None if the version is not valid
+    /// semver
+    pub fn matches_str(&self, version: &str) -> Option<bool> {
+        let version = SemVer::new(version.trim().trim_start_matches('v'))?;
+        Some(self.matches(&version))
+    }
+}
+
+/// parses a set of comparators separated by whitespace (or a hyphen range)
+fn parse_comparator_set(s: &str) -> Option<Vec<Comparator>> {
+    let tokens = tokenize(s);
+    if tokens.len() == 3 && tokens[1] == "-" {
+        return parse_hyphen_range(&tokens[0], &tokens[2]);
+    }
+    if tokens.is_empty() {
+        return Some(vec![comparator(Op::GreaterEq, semver(0, 0, 0, None))?]);
+    }
+    let mut comparators = Vec::new();
+    for token in tokens {
+        comparators.extend(parse_simple(&token)?);
+    }
+    Some(comparators)
+}
+
+/// splits a comparator set into tokens, joining operators separated from
+/// their version by whitespace (`>= 1.2.3`)
+fn tokenize(s: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut pending_op: Option<String> = None;
+    for word in s.split_whitespace() {
+        if let Some(op) = pending_op.take() {
+            tokens.push(format!("{}{}", op, word));
+        } else if word.chars().all(|c| "<>=~^".contains(c)) && word != "-" {
+            pending_op = Some(word.to_string());
+        } else {
+            tokens.push(word.to_string());
+        }
+    }
+    if let Some(op) = pending_op {
+        tokens.push(op);
+    }
+    tokens
+}
+
+/// parses a hyphen range like `1.2.3 - 2.3.4`
+fn parse_hyphen_range(from: &str, to: &str) -> Option<Vec<Comparator>> {
+    let from = Partial::parse(from)?;
+    let to = Partial::parse(to)?;
+    let mut comparators = vec![comparator(Op::GreaterEq, from.lower())?];
+    if to.major.is_some() {
+        if to.is_complete() {
+            comparators.push(comparator(Op::LessEq, to.lower())?);
+        } else {
+            comparators.push(comparator(Op::Less, to.upper_exclusive())?);
+        }
+    }
+    Some(comparators)
+}
+
+/// parses a single comparator which might expand to multiple comparators
+/// like `^1.2.3`, `~1.2`, `>=1.0.0` or `1.x`
+fn parse_simple(token: &str) -> Option<Vec<Comparator>> {
+    let op_len = token
+        .find(|c: char| !"<>=~^".contains(c))
+        .unwrap_or(token.len());
+    let (op, version) = token.split_at(op_len);
+    let partial = Partial::parse(version)?;
+    match op {
+        "" | "=" => Some(x_range(&partial)?),
+        "^" => caret(&partial),
+        "~" | "~>" => tilde(&partial),
+        ">=" => Some(vec![comparator(Op::GreaterEq, partial.lower())?]),
+        "<" => Some(vec![comparator(Op::Less, partial.lower())?]),
+        ">" => {
+            if partial.major.is_none() {
+                // nothing is greater than everything
+                Some(vec![comparator(Op::Less, semver(0, 0, 0, Some("0")))?])
+            } else if partial.is_complete() {
+                Some(vec![comparator(Op::Greater, partial.lower())?])
+            } else {
+                Some(vec![comparator(Op::GreaterEq, partial.upper_exclusive())?])
+            }
+        }
+        "<=" => {
+            if partial.major.is_none() {
+                Some(vec![comparator(Op::GreaterEq, semver(0, 0, 0, None))?])
+            } else if partial.is_complete() {
+                Some(vec![comparator(Op::LessEq, partial.lower())?])
+            } else {
+                Some(vec![comparator(Op::Less, partial.upper_exclusive())?])
+            }
+        }
+        _ => None,
+    }
+}
+
+/// expands a plain or wildcard version
+fn x_range(partial: &Partial) -> Option<Vec<Comparator>> {
+    if partial.major.is_none() {
+        Some(vec![comparator(Op::GreaterEq, semver(0, 0, 0, None))?])
+    } else if partial.is_complete() {
+        Some(vec![comparator(Op::Exact, partial.lower())?])
+    } else {
+        Some(vec![
+            comparator(Op::GreaterEq, partial.lower())?,
+            comparator(Op::Less, partial.upper_exclusive())?,
+        ])
+    }
+}
+
+/// expands a tilde range
+fn tilde(partial: &Partial) -> Option<Vec<Comparator>> {
+    let Some(major) = partial.major else {
+        return x_range(partial);
+    };
+    let upper = match partial.minor {
+        Some(minor) => semver(major, minor.checked_add(1)?, 0, Some("0")),
+        None => semver(major.checked_add(1)?, 0, 0, Some("0")),
+    };
+    Some(vec![
+        comparator(Op::GreaterEq, partial.lower())?,
+        comparator(Op::Less, upper)?,
+    ])
+}
+
+/// expands a caret range
+fn caret(partial: &Partial) -> Option<Vec<Comparator>> {
+    let Some(major) = partial.major else {
+        return x_range(partial);
+    };
+    let upper = match (major, partial.minor, partial.patch) {
+        (0, Some(0), Some(patch)) => semver(0, 0, patch.checked_add(1)?, Some("0")),
+        (0, Some(minor), _) => semver(0, minor.checked_add(1)?, 0, Some("0")),
+        (major, _, _) => semver(major.checked_add(1)?, 0, 0, Some("0")),
+    };
+    Some(vec![
+        comparator(Op::GreaterEq, partial.lower())?,
+        comparator(Op::Less, upper)?,
+    ])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// helper to check a range against a version
+    fn matches(range: &str, version: &str) -> bool {
+        VersionRange::parse(range)
+            .unwrap_or_else(|| panic!("could not parse range {}", range))
+            .matches_str(version)
+            .unwrap_or_else(|| panic!("could not parse version {}", version))
+    }
+
+    #[test]
+    fn test_caret() {
+        assert!(matches("^1.2.3", "1.2.3"));
+        assert!(matches("^1.2.3", "1.9.0"));
+        assert!(!matches("^1.2.3", "2.0.0"));
+        assert!(!matches("^1.2.3", "1.2.2"));
+        assert!(matches("^0.2.3", "0.2.9"));
+        assert!(!matches("^0.2.3", "0.3.0"));
+        assert!(matches("^0.0.3", "0.0.3"));
+        assert!(!matches("^0.0.3", "0.0.4"));
+        assert!(matches("^1.x", "1.5.0"));
+        assert!(!matches("^1.x", "2.0.0-beta.1"));
+    }
+
+    #[test]
+    fn test_tilde() {
+        assert!(matches("~1.2.3", "1.2.9"));
+        assert!(!matches("~1.2.3", "1.3.0"));
+        assert!(matches("~1", "1.9.9"));
+        assert!(!matches("~1", "2.0.0"));
+    }
+
+    #[test]
+    fn test_comparators() {
+        assert!(matches("<4.17.21", "4.17.20"));
+        assert!(!matches("<4.17.21", "4.17.21"));
+        assert!(matches("<=4.17.20", "4.17.20"));
+        assert!(matches(">=2.0.0 <2.1.3", "2.1.2"));
+        assert!(!matches(">=2.0.0 <2.1.3", "2.1.3"));
+        assert!(matches(">= 2.0.0 < 2.1.3", "2.0.0"));
+        assert!(matches(">1.2", "1.3.0"));
+        assert!(!matches(">1.2", "1.2.9"));
+        assert!(matches("<=1.2", "1.2.9"));
+        assert!(!matches("<=1.2", "1.3.0"));
+    }
+
+    #[test]
+    fn test_alternatives_and_wildcards() {
+        assert!(matches("<1.0.0 || >=2.0.0", "2.5.0"));
+        assert!(!matches("<1.0.0 || >=2.0.0", "1.5.0"));
+        assert!(matches("*", "0.0.1"));
+        assert!(matches("", "3.0.0"));
+        assert!(matches("1.x", "1.0.0"));
+        assert!(!matches("1.2.x", "1.3.0"));
+        assert!(matches("1.2.3", "1.2.3"));
+        assert!(!matches("1.2.3", "1.2.4"));
+    }
+
+    #[test]
+    fn test_hyphen() {
+        assert!(matches("1.0.0-rc1 - 2.1.8", "2.1.8"));
+        assert!(matches("1.0.0-rc1 - 2.1.8", "1.0.0-rc1"));
+        assert!(!matches("1.0.0-rc1 - 2.1.8", "2.1.9"));
+        assert!(matches("1.2 - 2.3", "2.3.5"));
+        assert!(!matches("1.2 - 2.3", "2.4.0"));
+    }
+
+    #[test]
+    fn test_upper_bound_overflow() {
+        // the upper bound of these ranges does not fit into a u32 component
+        for range in [
+            "^4294967295.0.0",
+            "^0.4294967295.0",
+            "^0.0.4294967295",
+            "~1.4294967295",
+            "~4294967295",
+            "4294967295.x",
+            "1.2 - 4294967295",
+            "<=1.4294967295",
+        ] {
+            assert_eq!(VersionRange::parse(range), None, "{}", range);
+        }
+        assert!(matches(">=4294967295.0.0", "4294967295.0.0"));
+        assert!(matches("<=4294967295.0.0", "1.0.0"));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(VersionRange::parse("latest"), None);
+        assert_eq!(VersionRange::parse(">=1.x.3"), None);
+        assert_eq!(
+            VersionRange::parse("^1.0.0").and_then(|r| r.matches_str("next")),
+            None
+        );
+    }
+}
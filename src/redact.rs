@@ -0,0 +1,489 @@
+//! This replaces package names and advisory identifiers in npm-audit
+//! reports with hashes
+//!
+//! for sharing reports without revealing the dependencies of a project
+
+use crate::audit::{
+    fnv1a, Action, Cve, Fix, NpmAuditData, NpmAuditDataV1, NpmAuditDataV2, Vulnerability,
+    FNV_OFFSET_BASIS,
+};
+use std::collections::BTreeSet;
+
+/// the replacement for a package name, the same name always gives the same
+/// replacement
+fn redact_name(name: &str) -> String {
+    format!("pkg-{:016x}", fnv1a(FNV_OFFSET_BASIS, name.as_bytes()))
+}
+
+/// the replacement for an advisory URL, the same URL always gives the same
+/// replacement
+fn redact_url(url: &str) -> String {
+    format!("url-{:016x}", fnv1a(FNV_OFFSET_BASIS, url.as_bytes()))
+}
+
+/// the replacement for a GHSA id, the same id always gives the same
+/// replacement
+fn redact_ghsa_id(id: &str) -> String {
+    format!("ghsa-{:016x}", fnv1a(FNV_OFFSET_BASIS, id.as_bytes()))
+}
+
+/// the replacement for a numeric advisory id, the same id always gives the
+/// same replacement
+///
+/// this keeps 53 bits of the hash so the replacement is still exact as a
+/// JavaScript number
+fn redact_id(id: u64) -> u64 {
+    fnv1a(FNV_OFFSET_BASIS, id.to_string().as_bytes()) >> 11
+}
+
+/// the replacement for the key of a version 1 advisory, matching the
+/// replacement of its numeric id
+fn redact_id_key(key: &str) -> String {
+    match key.parse() {
+        Ok(id) => redact_id(id).to_string(),
+        Err(_) => format!("id-{:016x}", fnv1a(FNV_OFFSET_BASIS, key.as_bytes())),
+    }
+}
+
+/// the replacement for a CVE id, the same id always gives the same
+/// replacement
+///
+/// the replacement is a CVE id with the year 0000 which no real CVE has, so
+/// CVE counts of the redacted report stay the same
+fn redact_cve(cve: &Cve) -> String {
+    format!(
+        "CVE-0000-{}",
+        fnv1a(FNV_OFFSET_BASIS, cve.as_str().as_bytes())
+    )
+}
+
+/// replaces all CVE ids in a free text, they are found like
+/// [crate::audit::extract_cves] finds them
+fn redact_cves(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut result = String::with_capacity(text.len());
+    let mut copied = 0;
+    let mut i = 0;
+    while i + 4 <= bytes.len() {
+        let at_word_start = i == 0 || !bytes[i - 1].is_ascii_alphanumeric();
+        if at_word_start && bytes[i..i + 4].eq_ignore_ascii_case(b"cve-") {
+            let end = bytes[i + 4..]
+                .iter()
+                .position(|b| !(b.is_ascii_digit() || *b == b'-'))
+                .map_or(bytes.len(), |p| p + i + 4);
+            if let Some(cve) = Cve::parse(&text[i..end]) {
+                result.push_str(&text[copied..i]);
+                result.push_str(&redact_cve(&cve));
+                copied = end;
+            }
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    result.push_str(&text[copied..]);
+    result
+}
+
+/// replaces all GHSA ids in a free text
+fn redact_ghsa_ids(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("GHSA-") {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest["GHSA-".len()..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+            .map_or(rest.len(), |end| end + "GHSA-".len());
+        result.push_str(&redact_ghsa_id(&rest[..end]));
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// redacts all package names in a node_modules path
+fn redact_node_path(path: &str) -> String {
+    let mut parts = Vec::new();
+    let mut segments = path.split('/');
+    while let Some(segment) = segments.next() {
+        if segment == "node_modules" || segment.is_empty() {
+            parts.push(segment.to_string());
+        } else if segment.starts_with('@') {
+            let name = match segments.next() {
+                Some(rest) => format!("{}/{}", segment, rest),
+                None => segment.to_string(),
+            };
+            parts.push(redact_name(&name));
+        } else {
+            parts.push(redact_name(segment));
+        }
+    }
+    parts.join("/")
+}
+
+/// replaces all occurrences of the package names in a free text as whole
+/// words and all GHSA and CVE ids
+fn redact_text(text: &str, names: &BTreeSet<String>) -> String {
+    let mut text = redact_cves(&redact_ghsa_ids(text));
+    for name in names.iter().filter(|n| n.starts_with('@')) {
+        text = text.replace(name.as_str(), &redact_name(name));
+    }
+    /// characters which can be part of an unscoped package name
+    fn is_name_char(c: char) -> bool {
+        c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-')
+    }
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    while let Some(start) = rest.find(is_name_char) {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+        let word = &rest[..end];
+        // a sentence ending after a package name
+        let name = word.trim_end_matches('.');
+        if names.contains(name) {
+            result.push_str(&redact_name(name));
+            result.push_str(&word[name.len()..]);
+        } else {
+            result.push_str(word);
+        }
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// the package names in a version 1 report
+fn names_v1(data: &NpmAuditDataV1) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    for advisory in data.advisories.values() {
+        names.extend(advisory.module_name.iter().cloned());
+        for finding in &advisory.findings {
+            names.extend(finding.paths.iter().flatten().cloned());
+        }
+    }
+    for action in &data.actions {
+        let (Action::Install {
+            module, resolves, ..
+        }
+        | Action::Update {
+            module, resolves, ..
+        }
+        | Action::Review {
+            module, resolves, ..
+        }) = action;
+        names.insert(module.to_owned());
+        for resolves in resolves {
+            names.extend(resolves.path.iter().cloned());
+        }
+    }
+    names.extend(data.muted.iter().flatten().cloned());
+    names
+}
+
+/// the package names in a version 2 report
+fn names_v2(data: &NpmAuditDataV2) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    for (name, package) in &data.vulnerabilities {
+        names.insert(name.to_owned());
+        names.insert(package.name.to_owned());
+        names.extend(package.effects.iter().cloned());
+        for vulnerability in &package.via {
+            match vulnerability {
+                Vulnerability::NameOnly(name) => {
+                    names.insert(name.to_owned());
+                }
+                Vulnerability::Full {
+                    name, dependency, ..
+                } => {
+                    names.insert(name.to_owned());
+                    names.insert(dependency.to_owned());
+                }
+                Vulnerability::SourceId(_) => {}
+            }
+        }
+        if let Fix::Full { name, .. } = &package.fix_available {
+            names.insert(name.to_owned());
+        }
+    }
+    names
+}
+
+/// redacts a version 1 report in place
+fn redact_v1(data: &mut NpmAuditDataV1) {
+    let names = names_v1(data);
+    data.advisories = std::mem::take(&mut data.advisories)
+        .into_iter()
+        .map(|(key, advisory)| (redact_id_key(&key), advisory))
+        .collect();
+    for advisory in data.advisories.values_mut() {
+        advisory.id = redact_id(advisory.id);
+        advisory.cves = advisory
+            .cves
+            .as_ref()
+            .map(|cves| cves.iter().map(|cve| redact_cves(cve)).collect());
+        advisory.module_name = advisory.module_name.as_deref().map(redact_name);
+        for finding in &mut advisory.findings {
+            for path in &mut finding.paths {
+                for name in path.iter_mut() {
+                    *name = redact_name(name);
+                }
+            }
+        }
+        advisory.title = redact_text(&advisory.title, &names);
+        advisory.overview = redact_text(&advisory.overview, &names);
+        advisory.recommendation = redact_text(&advisory.recommendation, &names);
+        advisory.references = advisory
+            .references
+            .as_deref()
+            .map(|r| redact_text(r, &names));
+        advisory.github_advisory_id = advisory.github_advisory_id.as_deref().map(redact_ghsa_id);
+        advisory.url = redact_url(&advisory.url);
+    }
+    for action in &mut data.actions {
+        let (Action::Install {
+            module, resolves, ..
+        }
+        | Action::Update {
+            module, resolves, ..
+        }
+        | Action::Review {
+            module, resolves, ..
+        }) = action;
+        *module = redact_name(module);
+        for resolves in resolves {
+            resolves.id = redact_id(resolves.id);
+            for name in &mut resolves.path {
+                *name = redact_name(name);
+            }
+        }
+    }
+    if let Some(muted) = &mut data.muted {
+        for name in muted {
+            *name = redact_name(name);
+        }
+    }
+}
+
+/// redacts a version 2 report in place
+fn redact_v2(data: &mut NpmAuditDataV2) {
+    let names = names_v2(data);
+    data.vulnerabilities = std::mem::take(&mut data.vulnerabilities)
+        .into_iter()
+        .map(|(name, mut package)| {
+            package.name = redact_name(&package.name);
+            for effect in &mut package.effects {
+                *effect = redact_name(effect);
+            }
+            for node in &mut package.nodes {
+                *node = redact_node_path(node);
+            }
+            for vulnerability in &mut package.via {
+                match vulnerability {
+                    Vulnerability::NameOnly(name) => *name = redact_name(name),
+                    Vulnerability::Full {
+                        source,
+                        name,
+                        dependency,
+                        title,
+                        url,
+                        ..
+                    } => {
+                        *source = redact_id(*source);
+                        *name = redact_name(name);
+                        *dependency = redact_name(dependency);
+                        *title = redact_text(title, &names);
+                        *url = redact_url(url);
+                    }
+                    Vulnerability::SourceId(source) => *source = redact_id(*source),
+                }
+            }
+            if let Fix::Full { name, .. } = &mut package.fix_available {
+                *name = redact_name(name);
+            }
+            (redact_name(&name), package)
+        })
+        .collect();
+}
+
+impl NpmAuditData {
+    /// a copy of the report with all package names replaced by hashes
+    ///
+    /// the same name is always replaced by the same hash (pkg- followed by
+    /// the 64 bit FNV-1a hash in hex) so the structure of the report stays
+    /// intact. Advisory URLs, GHSA ids, numeric advisory ids and CVEs are
+    /// hashed the same way since they identify the vulnerable packages,
+    /// numeric ids stay numbers and CVEs become CVE ids with the year 0000.
+    /// Severities, counts and version ranges are kept.
+    ///
+    /// redacted are the package names in report version 1 in module_name,
+    /// the findings paths, the actions and muted and in report version 2 the
+    /// keys of vulnerabilities, name, effects, nodes, the names in via and
+    /// fixAvailable. Occurrences of any of these names as whole words in
+    /// titles, overviews, recommendations and references are replaced too,
+    /// this might also replace ordinary words if a package has that name.
+    /// GHSA ids and CVEs in these texts are replaced as well. The ids
+    /// replaced are the keys and ids of the version 1 advisories and the ids
+    /// in their resolves and cves and the sources in the via of version 2.
+    pub fn redacted(&self) -> NpmAuditData {
+        let mut data = self.clone();
+        match &mut data {
+            NpmAuditData::Version1(data) => redact_v1(data),
+            NpmAuditData::Version2(data) => redact_v2(data),
+        }
+        data
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn test_redacted_v2() -> Result<(), Error> {
+        let data = NpmAuditData::Version2(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v2.json"
+        ))?);
+        let redacted = data.redacted();
+        let redacted_severities = redacted.package_severities();
+        assert_eq!(redacted_severities.len(), 6);
+        for (name, severity) in data.package_severities() {
+            assert_eq!(redacted_severities[&redact_name(&name)], severity);
+        }
+        let NpmAuditData::Version2(redacted) = redacted else {
+            panic!("expected a version 2 report");
+        };
+        assert_eq!(redacted.metadata.vulnerabilities.total, 6);
+        let lodash = &redacted.vulnerabilities[&redact_name("lodash")];
+        assert_eq!(lodash.name, redact_name("lodash"));
+        assert_eq!(
+            lodash.nodes,
+            vec![format!("node_modules/{}", redact_name("lodash"))]
+        );
+        assert_eq!(lodash.via[1].name(), redact_name("lodash"));
+        let json = serde_json::to_string(&redacted)?;
+        assert!(!json.contains("lodash"));
+        assert!(!json.contains("minimist"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_redacted_v1() -> Result<(), Error> {
+        let data = NpmAuditData::Version1(serde_json::from_str(include_str!(
+            "../fixtures/npm_audit_v1.json"
+        ))?);
+        let redacted = data.redacted();
+        let redacted_severities = redacted.package_severities();
+        assert_eq!(redacted_severities.len(), 4);
+        for (name, severity) in data.package_severities() {
+            assert_eq!(redacted_severities[&redact_name(&name)], severity);
+        }
+        let NpmAuditData::Version1(redacted) = redacted else {
+            panic!("expected a version 1 report");
+        };
+        let lodash = &redacted.advisories[&redact_id(1070355).to_string()];
+        let json = serde_json::to_string(lodash)?;
+        assert!(!json.contains("lodash"), "{}", json);
+        assert!(!json.contains("GHSA-"), "{}", json);
+        assert_eq!(
+            lodash.url,
+            redact_url("https://github.com/advisories/GHSA-35jh-r3h4-6jhm")
+        );
+        assert_eq!(lodash.id, redact_id(1070355));
+        assert_eq!(
+            lodash.cve_ids(),
+            Cve::parse(&redact_cve(
+                &Cve::parse("CVE-2021-23337").expect("valid CVE")
+            ))
+            .into_iter()
+            .collect::<Vec<_>>()
+        );
+        for action in &redacted.actions {
+            let (Action::Install { resolves, .. }
+            | Action::Update { resolves, .. }
+            | Action::Review { resolves, .. }) = action;
+            for resolves in resolves {
+                assert!(redacted.advisories.contains_key(&resolves.id.to_string()));
+            }
+        }
+        assert_eq!(
+            NpmAuditData::Version1(redacted).distinct_cve_count(),
+            data.distinct_cve_count()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_redacted_contains_no_names() -> Result<(), Error> {
+        let v1: NpmAuditDataV1 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v1.json"))?;
+        let v2: NpmAuditDataV2 =
+            serde_json::from_str(include_str!("../fixtures/npm_audit_v2.json"))?;
+        let names_v1 = names_v1(&v1);
+        let names_v2 = names_v2(&v2);
+        for (data, names) in [
+            (NpmAuditData::Version1(v1), names_v1),
+            (NpmAuditData::Version2(v2), names_v2),
+        ] {
+            let json = serde_json::to_string(&data.redacted())?;
+            assert!(!json.contains("GHSA-"), "{}", json);
+            assert!(!json.contains("https://github.com/advisories"), "{}", json);
+            for id in [
+                "1070355", "1067342", "1068263", "1005365", "1067329", "1066786",
+            ] {
+                assert!(!json.contains(id), "{} in {}", id, json);
+            }
+            assert!(!json.contains("CVE-20"), "{}", json);
+            assert!(!names.is_empty());
+            for name in &names {
+                assert!(!json.contains(name.as_str()), "{} in {}", name, json);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_redact_cves() {
+        let cve = Cve::parse("CVE-2020-8203").expect("valid CVE");
+        assert_eq!(
+            redact_text(
+                "Prototype Pollution (cve-2020-8203), see CVE-2020-8203.",
+                &BTreeSet::new()
+            ),
+            format!(
+                "Prototype Pollution ({}), see {}.",
+                redact_cve(&cve),
+                redact_cve(&cve)
+            )
+        );
+        assert_eq!(
+            redact_cves("XCVE-2020-8203 CVE-20"),
+            "XCVE-2020-8203 CVE-20"
+        );
+        assert!(Cve::parse(&redact_cve(&cve)).is_some());
+        assert_eq!(redact_id_key("1070355"), redact_id(1070355).to_string());
+    }
+
+    #[test]
+    fn test_redact_text() {
+        let names = BTreeSet::from(["ms".to_string(), "@babel/core".to_string()]);
+        assert_eq!(
+            redact_text("Denial of Service in ms. See @babel/core and msx", &names),
+            format!(
+                "Denial of Service in {}. See {} and msx",
+                redact_name("ms"),
+                redact_name("@babel/core")
+            )
+        );
+        assert_eq!(
+            redact_node_path("node_modules/@babel/core/node_modules/ms"),
+            format!(
+                "node_modules/{}/node_modules/{}",
+                redact_name("@babel/core"),
+                redact_name("ms")
+            )
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! This extracts npm package tarballs for auditing
+//!
+//! [crate::audit::AuditCommand::tarball] uses this, a package tarball (as
+//! produced by npm pack or downloaded from the registry) is a gzip compressed
+//! tar archive with all files below a package/ directory
+
+use std::fs::File;
+use std::hash::{BuildHasher, Hasher};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// how often [TempDir::new] tries another name if the directory already
+/// exists
+const TEMP_DIR_ATTEMPTS: u32 = 100;
+
+/// A temporary directory which is removed when this is dropped
+#[derive(Debug)]
+pub(crate) struct TempDir {
+    /// the path of the directory
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// creates a new, empty temporary directory
+    ///
+    /// the name has a random suffix and the directory is created with
+    /// [std::fs::create_dir] so an existing directory, e.g. one another user
+    /// created in a shared temporary directory, is never reused
+    pub(crate) fn new() -> Result<Self, crate::Error> {
+        let mut attempt = 0;
+        loop {
+            let path = std::env::temp_dir().join(format!(
+                "npm-parser-{}-{:016x}",
+                std::process::id(),
+                random_suffix()
+            ));
+            match std::fs::create_dir(&path) {
+                Ok(()) => return Ok(Self { path }),
+                Err(e)
+                    if e.kind() == std::io::ErrorKind::AlreadyExists
+                        && attempt < TEMP_DIR_ATTEMPTS =>
+                {
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// the path of the directory
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.path) {
+            debug!(
+                "Could not remove temporary directory {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// a random number for the names of temporary directories
+///
+/// [std::collections::hash_map::RandomState] is seeded randomly for each
+/// instance, this avoids a dependency on a random number crate
+fn random_suffix() -> u64 {
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default(),
+    );
+    hasher.finish()
+}
+
+/// extracts the package tarball into the destination directory
+///
+/// returns the directory containing the package.json, this is the package/
+/// directory if the tarball has one (which all tarballs produced by npm do)
+pub(crate) fn extract_tarball(tarball: &Path, destination: &Path) -> Result<PathBuf, crate::Error> {
+    let file = File::open(tarball)?;
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+    archive.unpack(destination)?;
+    let package_dir = destination.join("package");
+    if package_dir.join("package.json").is_file() {
+        Ok(package_dir)
+    } else {
+        Ok(destination.to_path_buf())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::Error;
+
+    /// creates a package tarball with only a package.json in the directory
+    pub(crate) fn create_tarball(dir: &Path) -> Result<PathBuf, Error> {
+        let path = dir.join("test-package-1.0.0.tgz");
+        let encoder =
+            flate2::write::GzEncoder::new(File::create(&path)?, flate2::Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        let package_json = br#"{"name":"test-package","version":"1.0.0"}"#;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(package_json.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, "package/package.json", &package_json[..])?;
+        builder.into_inner()?.finish()?;
+        Ok(path)
+    }
+
+    #[test]
+    fn test_extract_tarball() -> Result<(), Error> {
+        let dir = crate::test_dir("extract-tarball");
+        let tarball = create_tarball(&dir)?;
+        let temp = TempDir::new()?;
+        let package_dir = extract_tarball(&tarball, temp.path())?;
+        assert_eq!(package_dir, temp.path().join("package"));
+        assert!(package_dir.join("package.json").is_file());
+        let temp_path = temp.path().to_path_buf();
+        assert_ne!(TempDir::new()?.path(), temp_path);
+        drop(temp);
+        assert!(!temp_path.exists());
+        Ok(())
+    }
+}
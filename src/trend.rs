@@ -0,0 +1,116 @@
+//! This collects audit summaries over time, e.g. for charts of the number of
+//! vulnerabilities in a project
+
+use crate::audit::AuditSummary;
+use time::OffsetDateTime;
+
+/// A time series of audit summaries, ordered by time
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AuditTrend {
+    /// the summaries with the time of the audit, oldest first
+    pub points: Vec<(OffsetDateTime, AuditSummary)>,
+}
+
+/// The change of the counts between two consecutive points of a trend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AuditSummaryDelta {
+    /// the time of the earlier point
+    pub from: OffsetDateTime,
+    /// the time of the later point
+    pub to: OffsetDateTime,
+    /// change of the number of info level vulnerabilities
+    pub info: i64,
+    /// change of the number of low level vulnerabilities
+    pub low: i64,
+    /// change of the number of moderate level vulnerabilities
+    pub moderate: i64,
+    /// change of the number of high level vulnerabilities
+    pub high: i64,
+    /// change of the number of critical level vulnerabilities
+    pub critical: i64,
+    /// change of the number of vulnerabilities of all severities
+    pub total: i64,
+    /// change of the total number of dependencies
+    pub dependencies: i64,
+}
+
+/// the signed difference between two counts
+fn delta(from: u32, to: u32) -> i64 {
+    i64::from(to) - i64::from(from)
+}
+
+impl AuditTrend {
+    /// creates an empty trend
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// adds the summary of an audit at the given time
+    ///
+    /// the points stay ordered by time, a point with the same time as an
+    /// existing one is added after it
+    pub fn push(&mut self, at: OffsetDateTime, summary: AuditSummary) {
+        let index = self.points.partition_point(|(t, _)| *t <= at);
+        self.points.insert(index, (at, summary));
+    }
+
+    /// the changes between each point and the one before it
+    ///
+    /// empty for trends with fewer than two points
+    pub fn deltas(&self) -> Vec<AuditSummaryDelta> {
+        self.points
+            .windows(2)
+            .map(|pair| {
+                let (from, a) = pair[0];
+                let (to, b) = pair[1];
+                AuditSummaryDelta {
+                    from,
+                    to,
+                    info: delta(a.info, b.info),
+                    low: delta(a.low, b.low),
+                    moderate: delta(a.moderate, b.moderate),
+                    high: delta(a.high, b.high),
+                    critical: delta(a.critical, b.critical),
+                    total: delta(a.total(), b.total()),
+                    dependencies: delta(a.dependencies, b.dependencies),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::audit::parse_audit_str;
+    use crate::Error;
+    use time::Duration;
+
+    #[test]
+    fn test_trend_deltas_and_roundtrip() -> Result<(), Error> {
+        let start = OffsetDateTime::UNIX_EPOCH;
+        let before = parse_audit_str(include_str!("../fixtures/npm_audit_v2.json"), 2)?;
+        let after = parse_audit_str(include_str!("../fixtures/npm_audit_v2_clean.json"), 2)?;
+        let mut trend = AuditTrend::new();
+        trend.push(start + Duration::days(1), after.summary());
+        trend.push(start, before.summary());
+        assert_eq!(trend.points[0].0, start);
+        let deltas = trend.deltas();
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(deltas[0].from, start);
+        assert_eq!(deltas[0].total, -6);
+        assert_eq!(deltas[0].critical, -1);
+        let json = serde_json::to_string(&trend)?;
+        let reloaded: AuditTrend = serde_json::from_str(&json)?;
+        assert_eq!(reloaded, trend);
+        Ok(())
+    }
+
+    #[test]
+    fn test_trend_without_deltas() {
+        let mut trend = AuditTrend::new();
+        assert!(trend.deltas().is_empty());
+        trend.push(OffsetDateTime::UNIX_EPOCH, AuditSummary::default());
+        assert!(trend.deltas().is_empty());
+    }
+}
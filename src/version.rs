@@ -0,0 +1,178 @@
+//! This determines the version of npm
+//!
+//! the version decides which audit report format npm produces
+
+use std::path::Path;
+use std::process::Command;
+use std::str::from_utf8;
+use std::time::Duration;
+use tracing::debug;
+
+/// The version of npm as reported by npm --version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NpmVersion {
+    /// the output of npm --version without surrounding whitespace
+    raw: String,
+    /// the parsed version, None if the output could not be parsed
+    parsed: Option<versions::Versioning>,
+}
+
+impl NpmVersion {
+    /// interprets the output of npm --version
+    pub fn from_raw(raw: &str) -> Self {
+        let raw = raw.trim().to_string();
+        let parsed = versions::Versioning::new(&raw);
+        Self { raw, parsed }
+    }
+
+    /// a version which is already known, e.g. from an earlier probe
+    pub fn from_versioning(version: versions::Versioning) -> Self {
+        Self {
+            raw: version.to_string(),
+            parsed: Some(version),
+        }
+    }
+
+    /// the output of npm --version without surrounding whitespace
+    ///
+    /// this is available even if the version could not be parsed
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// the parsed version, None if the output could not be parsed
+    pub fn parsed(&self) -> Option<&versions::Versioning> {
+        self.parsed.as_ref()
+    }
+
+    /// the audit report format this version of npm produces
+    ///
+    /// see [crate::audit::report_format_for_npm_version]
+    pub fn report_format(&self) -> u8 {
+        crate::audit::report_format_for_npm_version(&self.raw)
+    }
+}
+
+impl std::fmt::Display for NpmVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+/// runs the given npm binary with --version
+pub fn probe_npm_version(binary: &Path) -> Result<NpmVersion, crate::Error> {
+    probe_npm_version_with(Command::new(binary), None)
+}
+
+/// runs the given command for npm with --version appended
+///
+/// returns [crate::Error::VersionProbeFailed] if npm can not be started or
+/// exits with an error and [crate::Error::Timeout] if it does not exit
+/// within the timeout, see [crate::process::output]
+pub(crate) fn probe_npm_version_with(
+    mut cmd: Command,
+    timeout: Option<Duration>,
+) -> Result<NpmVersion, crate::Error> {
+    cmd.args(["--version"]);
+    version_from_output(crate::process::output(&mut cmd, None, timeout))
+}
+
+/// like [probe_npm_version_with] but without blocking the async executor
+#[cfg(feature = "tokio")]
+pub(crate) async fn probe_npm_version_with_async(
+    mut cmd: Command,
+    timeout: Option<Duration>,
+) -> Result<NpmVersion, crate::Error> {
+    cmd.args(["--version"]);
+    version_from_output(crate::process::output_async(cmd, None, timeout).await)
+}
+
+/// interprets the result of running npm --version
+fn version_from_output(
+    output: Result<std::process::Output, crate::Error>,
+) -> Result<NpmVersion, crate::Error> {
+    let output = output.map_err(|e| match e {
+        crate::Error::StdIoError(e) => crate::Error::VersionProbeFailed {
+            stderr: e.to_string(),
+        },
+        e => e,
+    })?;
+    if !output.status.success() {
+        return Err(crate::Error::VersionProbeFailed {
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+    }
+
+    let version = NpmVersion::from_raw(from_utf8(&output.stdout)?);
+
+    debug!("Got version string {} from npm --version", version);
+
+    Ok(version)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn test_npm_version_from_raw() {
+        let version = NpmVersion::from_raw("10.8.2\n");
+        assert_eq!(version.raw(), "10.8.2");
+        assert_eq!(
+            version.parsed(),
+            versions::Versioning::new("10.8.2").as_ref()
+        );
+        assert_eq!(version.report_format(), 2);
+        let version = NpmVersion::from_raw("6.14.18\n");
+        assert_eq!(version.report_format(), 1);
+    }
+
+    #[test]
+    fn test_npm_version_unparseable() {
+        let version = NpmVersion::from_raw("");
+        assert_eq!(version.raw(), "");
+        assert_eq!(version.parsed(), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_probe_npm_version_failed() {
+        let dir = crate::test_dir("version-probe-failed");
+        let npm = crate::fake_npm(&dir, r#"echo "npm: broken install" >&2; exit 1"#);
+        match probe_npm_version(&npm) {
+            Err(Error::VersionProbeFailed { stderr }) => {
+                assert_eq!(stderr, "npm: broken install\n");
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(matches!(
+            probe_npm_version(&dir.join("does-not-exist")),
+            Err(Error::VersionProbeFailed { .. })
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_probe_npm_version() -> Result<(), Error> {
+        let dir = crate::test_dir("version-probe");
+        let npm = crate::fake_npm(&dir, "echo 10.8.2");
+        let version = probe_npm_version(&npm)?;
+        assert_eq!(version.raw(), "10.8.2");
+        assert_eq!(version.report_format(), 2);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_probe_npm_version_timeout() {
+        let dir = crate::test_dir("version-probe-timeout");
+        let npm = crate::fake_npm(&dir, "sleep 30; echo 10.8.2");
+        let start = std::time::Instant::now();
+        assert!(matches!(
+            probe_npm_version_with(Command::new(npm), Some(Duration::from_millis(200))),
+            Err(Error::Timeout(_))
+        ));
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+}
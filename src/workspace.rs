@@ -0,0 +1,155 @@
+//! This determines if a directory is part of an npm workspace, which decides
+//! the scope of an audit run in it
+//!
+//! only package.json files are read, npm is not called
+
+use crate::glob::glob_match;
+use std::path::{Path, PathBuf};
+
+/// Whether a directory is part of a workspace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkspaceContext {
+    /// the package.json in the directory declares workspaces, an audit here
+    /// covers the whole workspace
+    Root {
+        /// the workspace patterns from the package.json
+        workspaces: Vec<String>,
+    },
+    /// the directory is matched by the workspaces of a package.json in a
+    /// parent directory
+    Member {
+        /// the directory of the workspace root
+        root: PathBuf,
+    },
+    /// the directory is not part of a workspace
+    Standalone,
+}
+
+/// the workspace patterns declared in the package.json in the directory
+///
+/// both the npm form (a list) and the yarn form (an object with a packages
+/// list) are understood. None if there is no readable package.json or it
+/// declares no workspaces.
+fn workspace_patterns(dir: &Path) -> Option<Vec<String>> {
+    let content = std::fs::read_to_string(dir.join("package.json")).ok()?;
+    let package: serde_json::Value = serde_json::from_str(&content).ok()?;
+    let workspaces = package.get("workspaces")?;
+    let patterns = workspaces
+        .as_array()
+        .or_else(|| workspaces.get("packages").and_then(|p| p.as_array()))?;
+    Some(
+        patterns
+            .iter()
+            .filter_map(|p| p.as_str())
+            .map(|p| p.trim_start_matches("./").to_string())
+            .collect(),
+    )
+}
+
+/// determines whether the directory is a workspace root, a workspace member
+/// or neither
+///
+/// a directory is a member if a package.json in one of its parent
+/// directories declares workspaces matching its relative path. Unreadable
+/// package.json files are treated like missing ones. The directory is
+/// canonicalized first so relative paths like `.` find their parent
+/// directories too, this returns an error if it does not exist.
+pub fn detect_workspace_context(dir: &Path) -> Result<WorkspaceContext, crate::Error> {
+    let dir = std::fs::canonicalize(dir)?;
+    if let Some(workspaces) = workspace_patterns(&dir) {
+        return Ok(WorkspaceContext::Root { workspaces });
+    }
+    for root in dir.ancestors().skip(1) {
+        let Some(patterns) = workspace_patterns(root) else {
+            continue;
+        };
+        let Ok(relative) = dir.strip_prefix(root) else {
+            continue;
+        };
+        let relative = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if patterns.iter().any(|p| glob_match(p, &relative)) {
+            return Ok(WorkspaceContext::Member {
+                root: root.to_path_buf(),
+            });
+        }
+    }
+    Ok(WorkspaceContext::Standalone)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Error;
+
+    /// the canonical path of a fixture directory
+    fn fixture(path: &str) -> Result<PathBuf, Error> {
+        Ok(std::fs::canonicalize(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("fixtures")
+                .join(path),
+        )?)
+    }
+
+    #[test]
+    fn test_detect_workspace_root() -> Result<(), Error> {
+        assert_eq!(
+            detect_workspace_context(&fixture("workspace")?)?,
+            WorkspaceContext::Root {
+                workspaces: vec!["packages/*".to_string()]
+            }
+        );
+        assert_eq!(
+            detect_workspace_context(&fixture("workspace_yarn")?)?,
+            WorkspaceContext::Root {
+                workspaces: vec!["libs/**".to_string()]
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_workspace_member() -> Result<(), Error> {
+        assert_eq!(
+            detect_workspace_context(&fixture("workspace/packages/a")?)?,
+            WorkspaceContext::Member {
+                root: fixture("workspace")?
+            }
+        );
+        assert_eq!(
+            detect_workspace_context(&fixture("workspace_yarn/libs/b")?)?,
+            WorkspaceContext::Member {
+                root: fixture("workspace_yarn")?
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_workspace_member_relative() -> Result<(), Error> {
+        // cargo runs the tests in the directory of the crate
+        assert_eq!(
+            detect_workspace_context(Path::new("fixtures/workspace/packages/a/."))?,
+            WorkspaceContext::Member {
+                root: fixture("workspace")?
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_standalone() -> Result<(), Error> {
+        assert_eq!(
+            detect_workspace_context(&fixture("workspace/other")?)?,
+            WorkspaceContext::Standalone
+        );
+        assert!(matches!(
+            detect_workspace_context(Path::new("fixtures/does-not-exist")),
+            Err(Error::StdIoError(_))
+        ));
+        Ok(())
+    }
+}
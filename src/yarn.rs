@@ -0,0 +1,139 @@
+//! This parses the output of yarn audit --json (yarn 1)
+//!
+//! unlike npm yarn prints one JSON object per line, each with a type and the
+//! data for that type. The advisories use the same structure as npm audit
+//! report version 1 so [Advisory] and [Severity](crate::audit::Severity) are
+//! reused here.
+
+use crate::audit::{Action, Advisory, MetadataV1, Resolves};
+use std::collections::BTreeMap;
+
+/// an advisory record, yarn emits one per path to a vulnerable package
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct YarnAdvisory {
+    /// the path to the vulnerable package this record is about
+    pub resolution: Resolves,
+    /// the advisory, repeated in each record for the same advisory
+    pub advisory: Advisory,
+}
+
+/// an action record, a command which fixes some advisories
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct YarnAuditAction {
+    /// the yarn command to run
+    pub cmd: String,
+    /// does the command involve a semver major update
+    pub is_breaking: bool,
+    /// the action in the same form as in npm audit report version 1
+    pub action: Action,
+}
+
+/// a line of the yarn audit output this crate knows about
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "camelCase")]
+pub enum YarnAuditRecord {
+    /// an advisory for one path to a vulnerable package
+    AuditAdvisory(Box<YarnAdvisory>),
+    /// an action to fix advisories
+    AuditAction(YarnAuditAction),
+    /// the vulnerability and dependency counts, printed last
+    AuditSummary(MetadataV1),
+}
+
+/// The records of yarn audit --json combined into one report
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct YarnAuditReport {
+    /// advisories by id, each advisory only once
+    pub advisories: BTreeMap<u64, Advisory>,
+    /// the paths to vulnerable packages from all advisory records
+    pub resolutions: Vec<Resolves>,
+    /// the actions to fix advisories
+    pub actions: Vec<YarnAuditAction>,
+    /// the vulnerability and dependency counts
+    pub summary: MetadataV1,
+}
+
+/// parses a single line of the yarn output
+///
+/// returns None for empty lines and types other than the audit records, e.g.
+/// the info and warning lines yarn prints about the project
+pub fn parse_yarn_audit_record(line: &str) -> Result<Option<YarnAuditRecord>, crate::Error> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let value: serde_json::Value = serde_json::from_str(line)?;
+    if !matches!(
+        value.get("type").and_then(serde_json::Value::as_str),
+        Some("auditAdvisory" | "auditAction" | "auditSummary")
+    ) {
+        return Ok(None);
+    }
+    let record = serde_path_to_error::deserialize(&value)?;
+    Ok(Some(record))
+}
+
+/// parses the output of yarn audit --json
+///
+/// returns [crate::Error::NoReport] if the output contains no summary record
+pub fn parse_yarn_audit(input: &str) -> Result<YarnAuditReport, crate::Error> {
+    let mut advisories = BTreeMap::new();
+    let mut resolutions = Vec::new();
+    let mut actions = Vec::new();
+    let mut summary = None;
+    for line in input.lines() {
+        match parse_yarn_audit_record(line)? {
+            Some(YarnAuditRecord::AuditAdvisory(record)) => {
+                let YarnAdvisory {
+                    resolution,
+                    advisory,
+                } = *record;
+                resolutions.push(resolution);
+                advisories.entry(advisory.id).or_insert(advisory);
+            }
+            Some(YarnAuditRecord::AuditAction(action)) => actions.push(action),
+            Some(YarnAuditRecord::AuditSummary(metadata)) => summary = Some(metadata),
+            None => {}
+        }
+    }
+    Ok(YarnAuditReport {
+        advisories,
+        resolutions,
+        actions,
+        summary: summary.ok_or(crate::Error::NoReport)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::audit::Severity;
+    use crate::Error;
+
+    #[test]
+    fn test_parse_yarn_audit() -> Result<(), Error> {
+        let report = parse_yarn_audit(include_str!("../fixtures/yarn_audit.ndjson"))?;
+        assert_eq!(
+            report.advisories.keys().copied().collect::<Vec<_>>(),
+            vec![1067342, 1070355]
+        );
+        assert_eq!(report.advisories[&1070355].severity, Severity::High);
+        let minimist = &report.advisories[&1067342];
+        assert_eq!(minimist.found_by.as_deref(), Some("Snyk Security Team"));
+        assert_eq!(minimist.reported_by.as_deref(), Some("Snyk Security Team"));
+        assert_eq!(report.resolutions.len(), 3);
+        assert!(report.resolutions[1].dev);
+        assert_eq!(report.actions.len(), 1);
+        assert_eq!(report.actions[0].cmd, "yarn upgrade lodash@^4.17.21");
+        assert_eq!(report.summary.total_dependencies, 412);
+        assert_eq!(report.summary.vulnerabilities.high, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_yarn_audit_without_summary() {
+        let input = "{\"type\":\"info\",\"data\":\"fetching\"}\n\n";
+        assert!(matches!(parse_yarn_audit(input), Err(Error::NoReport)));
+    }
+}